@@ -0,0 +1,244 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::topology::parse_digits;
+use crate::{Error, Location};
+
+/// Hub port, formatted and parsed as `<hub location>:<port>`, for example `1-2.3:4`
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PortLocation {
+    /// Location of the hub
+    pub hub: Location,
+    /// Port of the hub
+    pub port: u8,
+}
+
+impl PortLocation {
+    /// Create a port location
+    pub fn new(hub: Location, port: u8) -> Self {
+        Self { hub, port }
+    }
+
+    /// Location of the device attached to the port
+    pub fn device(&self) -> Location {
+        self.hub.child(self.port)
+    }
+}
+
+impl Serialize for PortLocation {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl std::fmt::Display for PortLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.hub, self.port)
+    }
+}
+
+impl FromStr for PortLocation {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidLocation(s.to_string());
+        let (hub, port) = s.rsplit_once(':').ok_or_else(invalid)?;
+        let hub = hub.parse::<Location>().map_err(|_| invalid())?;
+        match parse_digits(port, 10) {
+            Some(port) if port > 0 => Ok(Self { hub, port }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Hub ports, parsed from a list of ports and port ranges such as `1,3-5`, or `all`
+#[derive(Clone, Debug, PartialEq)]
+pub enum PortList {
+    /// All ports of the hub
+    All,
+    /// Listed ports, sorted and without duplicates
+    Ports(Vec<u8>),
+}
+
+impl PortList {
+    /// Ports of a hub with `port_count` ports
+    pub fn ports(&self, port_count: u8) -> Vec<u8> {
+        match self {
+            Self::All => (1..=port_count).collect(),
+            Self::Ports(ports) => ports.clone(),
+        }
+    }
+}
+
+impl FromStr for PortList {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "all" {
+            return Ok(Self::All);
+        }
+        let invalid = || Error::InvalidPortList(s.to_string());
+        let port = |p: &str| match parse_digits::<u8>(p, 10) {
+            Some(port) if port > 0 => Ok(port),
+            _ => Err(invalid()),
+        };
+        let mut ports = Vec::new();
+        for item in s.split(',') {
+            let (first, last) = match item.split_once('-') {
+                Some((first, last)) => (port(first)?, port(last)?),
+                None => (port(item)?, port(item)?),
+            };
+            if first > last {
+                return Err(invalid());
+            }
+            ports.extend(first..=last);
+        }
+        ports.sort();
+        ports.dedup();
+        Ok(Self::Ports(ports))
+    }
+}
+
+/// Named hub port
+///
+/// The companion is the other half of a USB 3 hub, the port with the same number on the
+/// companion hub is switched together with the port.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PortAlias {
+    /// Hub port
+    pub port: PortLocation,
+    /// Location of the companion hub
+    pub companion: Option<Location>,
+}
+
+impl PortAlias {
+    /// Create an alias for a port without companion
+    pub fn new(port: PortLocation) -> Self {
+        Self {
+            port,
+            companion: None,
+        }
+    }
+
+    /// Locations of the hub and the companion hub
+    pub fn hubs(&self) -> impl Iterator<Item = &Location> {
+        std::iter::once(&self.port.hub).chain(self.companion.iter())
+    }
+}
+
+/// Alias as stored in TOML, either a port or a table with port and companion
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub(crate) enum AliasEntry {
+    Port(String),
+    Companion {
+        port: String,
+        #[serde(default)]
+        companion: Option<String>,
+    },
+}
+
+/// Table of port aliases
+///
+/// Aliases are stored in TOML as names mapped to ports, optionally with a companion hub
+///
+/// ```toml
+/// dut-rpi4 = "1-2.3:4"
+/// dut-usb3 = { port = "2-1:1", companion = "1-1" }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Aliases {
+    entries: BTreeMap<String, PortAlias>,
+}
+
+impl Aliases {
+    /// Parse aliases from TOML
+    pub fn from_toml(toml: &str) -> Result<Self, Error> {
+        toml::from_str::<BTreeMap<String, AliasEntry>>(toml)
+            .map_err(|e| e.to_string())
+            .and_then(Self::from_entries)
+            .map_err(|message| Error::InvalidAliases {
+                path: None,
+                message,
+            })
+    }
+
+    /// Create aliases from deserialized entries
+    pub(crate) fn from_entries(file: BTreeMap<String, AliasEntry>) -> Result<Self, String> {
+        let mut entries = BTreeMap::new();
+        for (name, entry) in file {
+            let (port, companion) = match entry {
+                AliasEntry::Port(port) => (port, None),
+                AliasEntry::Companion { port, companion } => (port, companion),
+            };
+            let port = port
+                .parse::<PortLocation>()
+                .map_err(|e| format!("{}, {}", name, e))?;
+            let companion = companion
+                .map(|c| c.parse::<Location>())
+                .transpose()
+                .map_err(|e| format!("{}, {}", name, e))?;
+            entries.insert(name, PortAlias { port, companion });
+        }
+        Ok(Self { entries })
+    }
+
+    /// Load aliases from a TOML file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let toml = std::fs::read_to_string(path).map_err(|source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_toml(&toml).map_err(|e| match e {
+            Error::InvalidAliases { message, .. } => Error::InvalidAliases {
+                path: Some(path.to_path_buf()),
+                message,
+            },
+            e => e,
+        })
+    }
+
+    /// Add aliases, replacing existing aliases with the same name
+    pub fn extend(&mut self, aliases: Aliases) {
+        self.entries.extend(aliases.entries);
+    }
+
+    /// Add an alias, replacing an existing alias with the same name
+    pub fn insert<S: Into<String>>(&mut self, name: S, alias: PortAlias) {
+        self.entries.insert(name.into(), alias);
+    }
+
+    /// Iterate over all aliases, ordered by name
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &PortAlias)> {
+        self.entries
+            .iter()
+            .map(|(name, alias)| (name.as_str(), alias))
+    }
+
+    /// Get the alias with the given name
+    pub fn get(&self, name: &str) -> Option<&PortAlias> {
+        self.entries.get(name)
+    }
+
+    /// Find the name of the alias for a port
+    pub fn name_of(&self, port: &PortLocation) -> Option<&str> {
+        self.iter()
+            .find(|(_, alias)| &alias.port == port)
+            .map(|(name, _)| name)
+    }
+
+    /// Resolve an alias name, or a port given as `<hub location>:<port>`
+    pub fn resolve(&self, name: &str) -> Result<PortAlias, Error> {
+        if let Some(alias) = self.get(name) {
+            return Ok(alias.clone());
+        }
+        match name.parse::<PortLocation>() {
+            Ok(port) => Ok(PortAlias::new(port)),
+            Err(_) => Err(Error::UnknownAlias(name.to_string())),
+        }
+    }
+}
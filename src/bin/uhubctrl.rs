@@ -1,20 +1,27 @@
 use std::collections::BTreeMap;
-use std::io::Write;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use clap::Parser;
-use nusb::MaybeFuture;
-use regex::Regex;
+use nusb::DeviceInfo;
+use serde::Serialize;
 
-use usb_hub_control::{Error, Hub};
-
-const DEVICE_CLASS_HUB: u8 = 0x09;
+use usb_hub_control::{
+    Aliases, Config, ContainerId, DEFAULT_TIMEOUT, DEVICE_CLASS_HUB, DeviceCapability,
+    DeviceSelector, DeviceSummary, Error, Event, FoundDevice, Hub, HubState, Location,
+    LogicalPowerSwitchingMode, OverCurrentProtectionMode, PortAlias, PortControlBackend, PortList,
+    PortLocation, PortState, PortStatus, Quirk, Quirks, SYSFS_ROOT, Snapshot, Sysfs, Topology,
+    UdevRule, parse_digits,
+};
 
 fn describe_device<W: Write>(
     output: &mut W,
-    key: &Vec<u8>,
-    info_map: &BTreeMap<Vec<u8>, nusb::DeviceInfo>,
-) -> Result<(), nusb::Error> {
-    let info = match info_map.get(key) {
+    location: &Location,
+    topology: &Topology,
+) -> Result<(), Error> {
+    let info = match topology.device(location) {
         Some(info) => info,
         None => return Ok(()),
     };
@@ -32,19 +39,125 @@ fn describe_device<W: Write>(
     Ok(())
 }
 
+/// Filters of the hubs and ports shown by `list`
+///
+/// With any hub filter the matching hubs are listed one after the other, without descending
+/// into the hubs attached to their ports.
+#[derive(clap::Args, Debug, Default)]
+struct ListFilter {
+    /// Only hubs at or below the location
+    #[arg(short, long)]
+    location: Option<Location>,
+
+    /// Only hubs with the given VID:PID, can be repeated
+    #[arg(long = "vid-pid")]
+    vid_pids: Vec<VidPid>,
+
+    /// Only hubs with the given container id
+    #[arg(short, long, conflicts_with = "read_only")]
+    container_id: Option<ContainerId>,
+
+    /// Only hubs with individual port power switching
+    #[arg(short, long, conflicts_with = "read_only")]
+    switchable: bool,
+
+    /// Only ports with a connection
+    #[arg(long)]
+    connected: bool,
+
+    /// Only ports with a device with the given VID:PID attached
+    #[arg(long)]
+    device: Option<VidPid>,
+
+    /// Only ports with a device with the given serial number attached
+    #[arg(long)]
+    device_serial: Option<String>,
+}
+
+impl ListFilter {
+    /// Check if hubs are selected, rather than listing all hubs from the top
+    fn selects_hubs(&self) -> bool {
+        self.location.is_some()
+            || !self.vid_pids.is_empty()
+            || self.container_id.is_some()
+            || self.switchable
+            || self.selects_devices()
+    }
+
+    fn selects_devices(&self) -> bool {
+        self.device.is_some() || self.device_serial.is_some()
+    }
+
+    /// Check the filters which do not require opening the hub
+    fn matches_hub(&self, location: &Location, vendor_id: u16, product_id: u16) -> bool {
+        self.location.as_ref().is_none_or(|l| location.is_within(l))
+            && (self.vid_pids.is_empty()
+                || self
+                    .vid_pids
+                    .iter()
+                    .any(|v| v.matches(vendor_id, product_id)))
+    }
+
+    /// Check the filters which require an opened hub
+    fn matches_opened_hub(&self, hub: &Hub) -> bool {
+        self.container_id
+            .as_ref()
+            .is_none_or(|c| hub.container_id().as_ref() == Some(c))
+            && (!self.switchable
+                || hub.power_switching_mode() == LogicalPowerSwitchingMode::IndividualPort)
+    }
+
+    fn matches_device(&self, vendor_id: u16, product_id: u16, serial: Option<&str>) -> bool {
+        self.device.is_none_or(|d| d.matches(vendor_id, product_id))
+            && self
+                .device_serial
+                .as_deref()
+                .is_none_or(|s| serial == Some(s))
+    }
+
+    /// Check if a port is shown, `device` is the VID, PID and serial of the attached device
+    fn matches_port(&self, connection: bool, device: Option<(u16, u16, Option<&str>)>) -> bool {
+        (!self.connected || connection)
+            && (!self.selects_devices()
+                || device.is_some_and(|(vendor_id, product_id, serial)| {
+                    self.matches_device(vendor_id, product_id, serial)
+                }))
+    }
+
+    /// Hubs of the topology matching the filters, opened
+    fn hubs(&self, topology: &Topology, quirks: &Quirks, timeout: Duration) -> Vec<Hub> {
+        topology
+            .hubs()
+            .filter(|(location, info)| {
+                self.matches_hub(location, info.vendor_id(), info.product_id())
+                    && (!self.selects_devices()
+                        || topology.ports(location).any(|(_, d)| {
+                            self.matches_device(d.vendor_id(), d.product_id(), d.serial_number())
+                        }))
+            })
+            .filter_map(|(location, info)| match Hub::open(info, quirks, timeout) {
+                Ok(hub) => Some(hub),
+                Err(e) => {
+                    eprintln!("Skipping hub {}, {}", location, e);
+                    None
+                }
+            })
+            .filter(|hub| self.matches_opened_hub(hub))
+            .collect()
+    }
+}
+
 fn describe_hub<W: Write>(
     output: &mut W,
-    key: &Vec<u8>,
-    info_map: &BTreeMap<Vec<u8>, nusb::DeviceInfo>,
+    hub: &Hub,
+    topology: &Topology,
+    quirks: &Quirks,
+    filter: &ListFilter,
 ) -> Result<(), Error> {
-    let info = match info_map.get(key) {
-        Some(info) => info,
-        None => return Ok(()),
-    };
+    let info = hub.info();
+    let location = hub.location();
     let align = info.port_chain().len().saturating_sub(1) * 2;
 
-    let hub = Hub::from_device_info(info)?;
-
     let key_string = format!(
         "{}.{}",
         info.busnum(),
@@ -55,34 +168,19 @@ fn describe_hub<W: Write>(
             .join("-")
     );
 
-    let container_id_str = if let Some(c) = hub.container_id() {
-        let c = c.0;
-        format!(
-            "{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
-            c[0],
-            c[1],
-            c[2],
-            c[3],
-            c[4],
-            c[5],
-            c[6],
-            c[7],
-            c[8],
-            c[9],
-            c[10],
-            c[11],
-            c[12],
-            c[13],
-            c[14],
-            c[15]
-        )
-    } else {
-        String::new()
+    let container_id_str = hub
+        .container_id()
+        .map(|c| c.to_string())
+        .unwrap_or_default();
+
+    let quirk_str = match hub.quirk().map(|q| q.names()) {
+        Some(names) if !names.is_empty() => format!(" quirks:{}", names.join(",")),
+        _ => String::new(),
     };
 
     let _ = writeln!(
         output,
-        "{} {:04x}:{:04x} {:02x} {:02x} {:02x} {:04x} {} {}",
+        "{} {:04x}:{:04x} {:02x} {:02x} {:02x} {:04x} {} {}{}",
         key_string,
         info.vendor_id(),
         info.product_id(),
@@ -92,12 +190,24 @@ fn describe_hub<W: Write>(
         info.device_version(),
         hub.port_count(),
         container_id_str,
+        quirk_str,
     );
 
     for port in 1..=hub.port_count() {
-        let mut port_key = key.clone();
-        port_key.push(port);
-        let connection = match hub.port_status(port) {
+        let port_location = location.child(port);
+        let status = hub.port_status(port);
+        // Port status errors are reported and the port is treated as connected
+        let connection = status.as_ref().map_or(true, |s| s.connection());
+        let device_info = if connection {
+            topology.device(&port_location)
+        } else {
+            None
+        };
+        let device = device_info.map(|d| (d.vendor_id(), d.product_id(), d.serial_number()));
+        if !filter.matches_port(connection, device) {
+            continue;
+        }
+        match status {
             Ok(status) => {
                 let connection = if status.connection() {
                     " connection"
@@ -111,136 +221,2302 @@ fn describe_hub<W: Write>(
                     ""
                 };
                 let powered = if status.powered() { " powered" } else { "" };
+                let label = hub
+                    .port_label(port)
+                    .map(|l| format!("[{}]", l))
+                    .unwrap_or_default();
                 let _ = write!(
                     output,
-                    "{:align$} {} {:04x}{}{}{}{} ",
-                    "", port, status.0, connection, enabled, overcurrent, powered
+                    "{:align$} {}{} {:04x}{}{}{}{} ",
+                    "",
+                    port,
+                    label,
+                    status.field(),
+                    connection,
+                    enabled,
+                    overcurrent,
+                    powered
                 );
-                status.connection()
             }
             Err(e) => {
                 eprintln!("Port status {} failed, {}", port, e);
-                true
             }
-        };
-        if connection {
-            match info_map.get(&port_key) {
-                Some(device_info) => {
-                    if device_info.class() != DEVICE_CLASS_HUB {
-                        describe_device(output, &port_key, info_map)?;
-                        let _ = writeln!(output);
-                    } else {
-                        describe_hub(output, &port_key, info_map)?;
-                    }
-                }
-                None => {
-                    let _ = writeln!(output);
-                }
+        }
+        match device_info {
+            Some(device_info)
+                if device_info.class() == DEVICE_CLASS_HUB && !filter.selects_hubs() =>
+            {
+                let child = Hub::open(device_info, quirks, hub.timeout())?;
+                describe_hub(output, &child, topology, quirks, filter)?;
+            }
+            Some(_) => {
+                describe_device(output, &port_location, topology)?;
+                let _ = writeln!(output);
+            }
+            None => {
+                let _ = writeln!(output);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn hubs_with_container_id(
+    topology: &Topology,
+    container_id: &ContainerId,
+    quirks: &Quirks,
+    timeout: Duration,
+) -> Vec<Hub> {
+    topology
+        .hubs()
+        .filter_map(|(location, info)| match Hub::open(info, quirks, timeout) {
+            Ok(hub) => Some(hub),
+            Err(e) => {
+                eprintln!("Skipping hub {}, {}", location, e);
+                None
             }
+        })
+        .filter(|hub| hub.container_id().as_ref() == Some(container_id))
+        .collect()
+}
+
+fn describe_sysfs_hub<W: Write>(
+    output: &mut W,
+    location: &Location,
+    sysfs: &Sysfs,
+    filter: &ListFilter,
+) -> Result<(), Error> {
+    let align = location.port_chain().len().saturating_sub(1) * 2;
+
+    let hub = sysfs.hub(location)?;
+    let device = &hub.device;
+
+    let _ = writeln!(
+        output,
+        "{} {:04x}:{:04x} {:02x} {:02x} {:02x} {:04x} {}",
+        location,
+        device.vendor_id,
+        device.product_id,
+        device.class,
+        device.subclass,
+        device.protocol,
+        device.device_version,
+        hub.port_count(),
+    );
+
+    for port in hub.ports.iter() {
+        let port_location = location.child(port.port);
+        let device = sysfs.device(&port_location).ok();
+        if !filter.matches_port(
+            port.connected(),
+            device
+                .as_ref()
+                .map(|d| (d.vendor_id, d.product_id, d.serial_number.as_deref())),
+        ) {
+            continue;
+        }
+        let state = port.state.as_deref().unwrap_or("unknown");
+        let connect_type = port
+            .connect_type
+            .as_ref()
+            .map(|c| format!(" {}", c))
+            .unwrap_or_default();
+        let disabled = if port.disabled == Some(true) {
+            " disabled"
         } else {
-            let _ = writeln!(output);
+            ""
+        };
+        let overcurrent = match port.over_current_count {
+            Some(count) if count > 0 => format!(" overcurrent:{}", count),
+            _ => String::new(),
+        };
+        let _ = write!(
+            output,
+            "{:align$} {} {}{}{}{} ",
+            "", port.port, state, connect_type, disabled, overcurrent
+        );
+        match device {
+            Some(device) if device.is_hub() && !filter.selects_hubs() => {
+                describe_sysfs_hub(output, &port_location, sysfs, filter)?;
+            }
+            Some(device) => {
+                let _ = writeln!(
+                    output,
+                    "{} {:04x}:{:04x} {} {} {}",
+                    port_location,
+                    device.vendor_id,
+                    device.product_id,
+                    device.manufacturer.as_deref().unwrap_or(""),
+                    device.product.as_deref().unwrap_or(""),
+                    device.serial_number.as_deref().unwrap_or("")
+                );
+            }
+            None => {
+                let _ = writeln!(output);
+            }
         }
     }
     Ok(())
 }
 
-fn list(info_map: &BTreeMap<Vec<u8>, nusb::DeviceInfo>) -> Result<(), Error> {
+/// Hubs shown by a read-only list, top-level hubs unless hubs are selected by the filter
+fn sysfs_hubs(sysfs: &Sysfs, filter: &ListFilter) -> Result<Vec<Location>, Error> {
+    let devices = sysfs.devices()?;
+    let hubs = devices
+        .iter()
+        .filter(|device| device.is_hub())
+        .filter(|device| {
+            if filter.selects_hubs() {
+                filter.matches_hub(&device.location, device.vendor_id, device.product_id)
+                    && (!filter.selects_devices()
+                        || devices.iter().any(|d| {
+                            d.location
+                                .parent()
+                                .is_some_and(|(p, _)| p == device.location)
+                                && filter.matches_device(
+                                    d.vendor_id,
+                                    d.product_id,
+                                    d.serial_number.as_deref(),
+                                )
+                        }))
+            } else {
+                device.location.depth() == 1
+            }
+        })
+        .map(|device| device.location.clone())
+        .collect();
+    Ok(hubs)
+}
+
+fn list_sysfs(sysfs: &Sysfs, filter: &ListFilter) -> Result<(), Error> {
     let mut buffer = Vec::new();
-    for (key, info) in info_map.iter() {
-        if key.len() == 2 && info.class() == DEVICE_CLASS_HUB {
-            describe_hub(&mut buffer, key, info_map)?;
-        }
+    for location in sysfs_hubs(sysfs, filter)? {
+        describe_sysfs_hub(&mut buffer, &location, sysfs, filter)?;
     }
     let output = std::str::from_utf8(buffer.as_slice()).unwrap().to_string();
     println!("{}", output);
     Ok(())
 }
 
-/// Simple program to greet a person
-#[derive(clap::Parser, Debug)]
-#[command(version, about, long_about = None)]
-struct Args {
-    #[command(subcommand)]
-    command: Option<Commands>,
+/// Write items as a JSON object holding the items in an array named `key`, or as JSON lines
+fn write_json<T: Serialize>(key: &str, items: &[T], format: Format) {
+    match format {
+        Format::JsonLines => {
+            for item in items {
+                println!(
+                    "{}",
+                    serde_json::to_string(item).expect("serialize to JSON")
+                );
+            }
+        }
+        _ => {
+            let document = serde_json::json!({ key: items });
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&document).expect("serialize to JSON")
+            );
+        }
+    }
 }
 
-#[derive(clap::Subcommand, Debug)]
-enum Commands {
-    List,
-    Power {
-        #[arg(short, long)]
-        port: u8,
+fn list_sysfs_json(
+    sysfs: &Sysfs,
+    quirks: &Quirks,
+    filter: &ListFilter,
+    format: Format,
+) -> Result<(), Error> {
+    let mut hubs = Vec::new();
+    let locations = if filter.selects_hubs() {
+        sysfs_hubs(sysfs, filter)?
+    } else {
+        sysfs
+            .devices()?
+            .into_iter()
+            .filter(|device| device.is_hub())
+            .map(|device| device.location)
+            .collect()
+    };
+    for location in locations {
+        let mut hub = HubState::from_sysfs(&sysfs.hub(&location)?, sysfs, quirks);
+        hub.ports.retain(|port| {
+            filter.matches_port(
+                port.device.is_some(),
+                port.device
+                    .as_ref()
+                    .map(|d| (d.vendor_id, d.product_id, d.serial_number.as_deref())),
+            )
+        });
+        hubs.push(hub);
+    }
+    write_json("hubs", &hubs, format);
+    Ok(())
+}
 
-        #[arg(short, long)]
-        on: bool,
+fn authorize(
+    sysfs_root: &Path,
+    alias: &PortAlias,
+    authorized: bool,
+    dry_run: bool,
+) -> Result<(), Error> {
+    let mut sysfs = Sysfs::new(sysfs_root);
+    sysfs.set_dry_run(dry_run);
+    let port = alias.port.port;
+    // The device is attached to one of the companion hubs, depending on its speed
+    let hub = alias
+        .hubs()
+        .find(|hub| sysfs.device_path(&hub.child(port)).is_dir())
+        .unwrap_or(&alias.port.hub);
+    println!(
+        "PORT {} {} LOCATION {}",
+        port,
+        if authorized { "rebind" } else { "unbind" },
+        hub
+    );
+    sysfs.set_port_authorized(hub, port, authorized)
+}
 
-        #[arg(short, long)]
-        location: Option<String>,
-    },
+/// Vendor and product ID, parsed from `vvvv:pppp` in hexadecimal
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct VidPid {
+    vendor_id: u16,
+    product_id: u16,
 }
 
-fn main() {
-    env_logger::init();
-    let args = Args::parse();
+impl VidPid {
+    fn matches(&self, vendor_id: u16, product_id: u16) -> bool {
+        self.vendor_id == vendor_id && self.product_id == product_id
+    }
+}
 
-    let device_iter = nusb::list_devices().wait().unwrap();
-    let mut info_map = BTreeMap::new();
-    for info in device_iter {
-        let bus_num = info.busnum();
-        let mut key = vec![bus_num];
-        key.extend(info.port_chain());
-        info_map.insert(key, info);
+impl FromStr for VidPid {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid VID:PID {}, expected vvvv:pppp", s);
+        let (vendor_id, product_id) = s.split_once(':').ok_or_else(invalid)?;
+        Ok(Self {
+            vendor_id: parse_digits(vendor_id, 16).ok_or_else(invalid)?,
+            product_id: parse_digits(product_id, 16).ok_or_else(invalid)?,
+        })
     }
+}
 
-    match args.command {
-        Some(Commands::Power { port, on, location }) => {
-            let location_regex = Regex::new(
-                r"^(?<busnum>[[:digit:]]+)-(?<chain>(?:(?:[[:digit:]]+)[.])*(?:[[:digit:]]+))$",
-            )
-            .unwrap();
-            let key = if let Some(location) = location {
-                if let Some(captures) = location_regex.captures(location.as_str()) {
-                    if let (Some(b), Some(c)) = (captures.name("busnum"), captures.name("chain")) {
-                        let busnum = b.as_str().parse::<u8>().unwrap();
-                        let chain = c
-                            .as_str()
-                            .split('.')
-                            .filter_map(|v| v.parse::<u8>().ok())
-                            .collect::<Vec<u8>>();
-                        let mut key = vec![busnum];
-                        key.extend(chain);
-                        Some(key)
-                    } else {
-                        None
-                    }
-                } else {
+/// Hub selected by its descriptors rather than by location
+#[derive(clap::Args, Debug, Default)]
+struct HubSelector {
+    /// Select hub by VID:PID
+    #[arg(long = "vid-pid", conflicts_with_all = ["location", "container_id"])]
+    vid_pid: Option<VidPid>,
+
+    /// Select hub by serial number
+    #[arg(long, conflicts_with_all = ["location", "container_id"])]
+    serial: Option<String>,
+
+    /// Select hub by product string
+    #[arg(long, conflicts_with_all = ["location", "container_id"])]
+    product: Option<String>,
+}
+
+impl HubSelector {
+    fn is_empty(&self) -> bool {
+        self.vid_pid.is_none() && self.serial.is_none() && self.product.is_none()
+    }
+
+    fn matches(&self, info: &DeviceInfo) -> bool {
+        self.vid_pid
+            .is_none_or(|v| v.matches(info.vendor_id(), info.product_id()))
+            && self
+                .serial
+                .as_deref()
+                .is_none_or(|s| info.serial_number() == Some(s))
+            && self
+                .product
+                .as_deref()
+                .is_none_or(|p| info.product_string() == Some(p))
+    }
+
+    /// Select the single matching hub
+    ///
+    /// Without any selector the only hub with individual port power switching is selected. Root
+    /// hubs are never selected.
+    fn select(
+        &self,
+        topology: &Topology,
+        quirks: &Quirks,
+        timeout: Duration,
+    ) -> Result<Hub, String> {
+        let mut hubs = topology
+            .hubs()
+            .filter(|(location, info)| location.depth() >= 1 && self.matches(info))
+            .filter_map(|(location, info)| match Hub::open(info, quirks, timeout) {
+                Ok(hub) => Some(hub),
+                Err(e) => {
+                    eprintln!("Skipping hub {}, {}", location, e);
                     None
                 }
+            })
+            .filter(|hub| {
+                !self.is_empty()
+                    || hub.power_switching_mode() == LogicalPowerSwitchingMode::IndividualPort
+            })
+            .collect::<Vec<Hub>>();
+        if hubs.len() == 1 {
+            return Ok(hubs.remove(0));
+        }
+        let mut message = match (hubs.is_empty(), self.is_empty()) {
+            (true, true) => String::from("No hub given and no switchable hub found"),
+            (true, false) => String::from("No matching hub found"),
+            (false, true) => String::from(
+                "No hub given and several switchable hubs found, select one with --location, \
+                 --vid-pid, --serial or --product",
+            ),
+            (false, false) => String::from("Several matching hubs found"),
+        };
+        for hub in hubs.iter() {
+            let mut candidate = Vec::new();
+            let _ = describe_device(&mut candidate, &hub.location(), topology);
+            message.push_str(&format!(
+                "\n  {} {}",
+                hub.location(),
+                String::from_utf8_lossy(&candidate).trim_end()
+            ));
+        }
+        Err(message)
+    }
+}
+
+/// Bus number and device address, parsed from `bus:address` in decimal
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct BusAddress {
+    busnum: u8,
+    address: u8,
+}
+
+impl FromStr for BusAddress {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid bus address {}, expected bus:address", s);
+        let (busnum, address) = s.split_once(':').ok_or_else(invalid)?;
+        Ok(Self {
+            busnum: parse_digits(busnum, 10).ok_or_else(invalid)?,
+            address: parse_digits(address, 10).ok_or_else(invalid)?,
+        })
+    }
+}
+
+/// Device selected by its descriptors or bus address
+#[derive(clap::Args, Debug, Default)]
+struct DeviceArgs {
+    /// Device with the given VID:PID
+    #[arg(long)]
+    device: Option<VidPid>,
+
+    /// Device with the given serial number
+    #[arg(long)]
+    device_serial: Option<String>,
+
+    /// Device with the given bus number and address, as bus:address
+    #[arg(long)]
+    device_address: Option<BusAddress>,
+
+    /// Device with the given product string
+    #[arg(long)]
+    device_product: Option<String>,
+}
+
+impl DeviceArgs {
+    fn selector(&self) -> DeviceSelector {
+        DeviceSelector {
+            vendor_id: self.device.map(|d| d.vendor_id),
+            product_id: self.device.map(|d| d.product_id),
+            serial_number: self.device_serial.clone(),
+            bus_address: self.device_address.map(|b| (b.busnum, b.address)),
+            product: self.device_product.clone(),
+        }
+    }
+}
+
+/// Resolve the parent hub and hub port of the single device matching the selector
+fn device_port(
+    selector: &DeviceSelector,
+    topology: &Topology,
+    quirks: &Quirks,
+    timeout: Duration,
+) -> Result<(Hub, u8), String> {
+    let found = topology
+        .find(selector)
+        .into_iter()
+        .filter(|found| found.port.is_some())
+        .collect::<Vec<FoundDevice>>();
+    let port = match found.as_slice() {
+        [] => return Err(String::from("No matching device found")),
+        [found] => found
+            .port
+            .clone()
+            .expect("found device is attached to a port"),
+        found => {
+            let mut message = String::from("Several matching devices found");
+            for found in found {
+                message.push_str(&format!("\n  {}", describe_found_device(found)));
+            }
+            return Err(message);
+        }
+    };
+    let info = topology
+        .device(&port.hub)
+        .ok_or_else(|| format!("No hub at {}", port.hub))?;
+    let hub = Hub::open(info, quirks, timeout).map_err(|e| e.to_string())?;
+    Ok((hub, port.port))
+}
+
+fn describe_found_device(found: &FoundDevice) -> String {
+    let device = &found.device;
+    let mut line = format!(
+        "{} {:04x}:{:04x}",
+        device.location, device.vendor_id, device.product_id
+    );
+    for part in [
+        device.manufacturer.as_deref(),
+        device.product.as_deref(),
+        device.serial_number.as_deref(),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        line.push(' ');
+        line.push_str(part);
+    }
+    match &found.port {
+        Some(port) => line.push_str(&format!(" port {}", port)),
+        None => line.push_str(" root hub"),
+    }
+    if !found.path.is_empty() {
+        let path = found
+            .path
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<String>>();
+        line.push_str(&format!(" path {}", path.join(" ")));
+    }
+    line
+}
+
+fn udev_rules(
+    topology: &Topology,
+    group: &str,
+    mode: &str,
+    vid_pids: &[VidPid],
+    locations: &[Location],
+    sysfs: bool,
+) -> String {
+    let mut rules = String::new();
+    rules.push_str("# USB hub access rules generated by uhubctrl udev-rules\n");
+    rules.push_str("# Install as /etc/udev/rules.d/52-usb-hub-control.rules and run\n");
+    rules.push_str("# udevadm control --reload-rules && udevadm trigger\n");
+
+    let mut matches: Vec<UdevRule> = Vec::new();
+    for (location, info) in topology.hubs() {
+        if !vid_pids.is_empty()
+            && !vid_pids
+                .iter()
+                .any(|v| v.matches(info.vendor_id(), info.product_id()))
+        {
+            continue;
+        }
+        if !locations.is_empty() && !locations.contains(location) {
+            continue;
+        }
+        // Without location filter all hubs with the same VID:PID share a rule
+        let location = if locations.is_empty() {
+            None
+        } else {
+            Some(location.clone())
+        };
+        let (vendor_id, product_id) = (info.vendor_id(), info.product_id());
+        if !matches.iter().any(|m| {
+            m.location == location && m.vendor_id == vendor_id && m.product_id == product_id
+        }) {
+            let description = format!(
+                "{} {}",
+                info.manufacturer_string().unwrap_or(""),
+                info.product_string().unwrap_or("")
+            );
+            matches.push(UdevRule {
+                location,
+                vendor_id,
+                product_id,
+                description: description.trim().to_string(),
+            });
+        }
+    }
+
+    for rule in matches {
+        rules.push('\n');
+        rules.push_str(&rule.format(group, mode, sysfs));
+    }
+    rules
+}
+
+fn list_json(
+    topology: &Topology,
+    quirks: &Quirks,
+    filter: &ListFilter,
+    format: Format,
+    timeout: Duration,
+) {
+    let mut hubs = filter
+        .hubs(topology, quirks, timeout)
+        .iter()
+        .map(|hub| HubState::read(hub, topology))
+        .collect::<Vec<HubState>>();
+    for hub in hubs.iter_mut() {
+        hub.ports.retain(|port| {
+            filter.matches_port(
+                port.status.is_none_or(|s| s.connection()),
+                port.device
+                    .as_ref()
+                    .map(|d| (d.vendor_id, d.product_id, d.serial_number.as_deref())),
+            )
+        });
+    }
+    write_json("hubs", &hubs, format);
+}
+
+fn list(
+    topology: &Topology,
+    quirks: &Quirks,
+    filter: &ListFilter,
+    timeout: Duration,
+) -> Result<(), Error> {
+    let mut buffer = Vec::new();
+    if filter.selects_hubs() {
+        for hub in filter.hubs(topology, quirks, timeout) {
+            describe_hub(&mut buffer, &hub, topology, quirks, filter)?;
+        }
+    } else {
+        for (_, info) in topology.top_level_hubs() {
+            let hub = Hub::open(info, quirks, timeout)?;
+            describe_hub(&mut buffer, &hub, topology, quirks, filter)?;
+        }
+    }
+    let output = std::str::from_utf8(buffer.as_slice()).unwrap().to_string();
+    println!("{}", output);
+    Ok(())
+}
+
+/// When to color the tree output
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum ColorChoice {
+    /// Color when writing to a terminal and NO_COLOR is not set
+    Auto,
+    /// Always color
+    Always,
+    /// Never color
+    Never,
+}
+
+impl ColorChoice {
+    fn enabled(&self) -> bool {
+        match self {
+            Self::Auto => std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal(),
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
+}
+
+const COLOR_GREEN: &str = "\x1b[32m";
+const COLOR_GREY: &str = "\x1b[90m";
+const COLOR_RED: &str = "\x1b[31m";
+const COLOR_RESET: &str = "\x1b[0m";
+
+/// Rendering of the hub tree
+struct Tree<'a> {
+    topology: &'a Topology,
+    quirks: &'a Quirks,
+    filter: &'a ListFilter,
+    color: bool,
+}
+
+impl Tree<'_> {
+    fn paint(&self, text: &str, color: &str) -> String {
+        if self.color {
+            format!("{}{}{}", color, text, COLOR_RESET)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Port state, green when powered, grey when off and red on over-current
+    fn port_state(&self, status: &PortStatus) -> String {
+        let mut state = vec![match (status.overcurrent(), status.powered()) {
+            (true, _) => "over-current",
+            (false, true) => "powered",
+            (false, false) => "off",
+        }];
+        if status.connection() {
+            state.push("connected");
+        }
+        if status.enabled() {
+            state.push("enabled");
+        }
+        let color = match (status.overcurrent(), status.powered()) {
+            (true, _) => COLOR_RED,
+            (false, true) => COLOR_GREEN,
+            (false, false) => COLOR_GREY,
+        };
+        self.paint(&state.join(" "), color)
+    }
+
+    fn device_name(info: &DeviceInfo) -> String {
+        let device = DeviceSummary::from_device_info(info);
+        let mut name = format!("{:04x}:{:04x}", device.vendor_id, device.product_id);
+        for part in [
+            device.manufacturer.as_deref(),
+            device.product.as_deref(),
+            device.speed,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            name.push(' ');
+            name.push_str(part);
+        }
+        name
+    }
+
+    fn hub_name(hub: &Hub) -> String {
+        let mode = match hub.power_switching_mode() {
+            LogicalPowerSwitchingMode::IndividualPort => "individual",
+            LogicalPowerSwitchingMode::Common => "ganged",
+            LogicalPowerSwitchingMode::None => "no switching",
+        };
+        format!(
+            "{} {} [{} ports, {}]",
+            hub.location(),
+            Self::device_name(&hub.info()),
+            hub.port_count(),
+            mode
+        )
+    }
+
+    fn hub<W: Write>(&self, output: &mut W, hub: &Hub) -> Result<(), Error> {
+        let _ = writeln!(output, "{}", Self::hub_name(hub));
+        self.ports(output, hub, "")
+    }
+
+    fn ports<W: Write>(&self, output: &mut W, hub: &Hub, prefix: &str) -> Result<(), Error> {
+        let location = hub.location();
+        let mut ports = Vec::new();
+        for port in 1..=hub.port_count() {
+            let status = hub.port_status(port);
+            // Port status errors are shown and the port is treated as connected
+            let connection = status.as_ref().map_or(true, |s| s.connection());
+            let device_info = if connection {
+                self.topology.device(&location.child(port))
             } else {
                 None
             };
-            if let Some(k) = key {
-                if let Some(info) = info_map.get(&k) {
-                    let hub = Hub::from_device_info(info).unwrap();
-                    println!(
-                        "PORT {} {} KEY {:?} {:02x} {:02x}",
-                        port,
-                        if on { "on" } else { "off" },
-                        k,
-                        info.busnum(),
-                        info.device_address()
-                    );
-                    if let Err(e) = hub.set_port_power(port, on) {
-                        eprint!("Failed to switch port, {}", e);
-                    }
-                }
+            let device = device_info.map(|d| (d.vendor_id(), d.product_id(), d.serial_number()));
+            if self.filter.matches_port(connection, device) {
+                ports.push((port, status, device_info));
             }
         }
-        _ => match list(&info_map) {
-            Ok(()) => (),
-            Err(ref e) => {
-                eprintln!("List failed, {}", e);
+
+        let count = ports.len();
+        for (index, (port, status, device_info)) in ports.into_iter().enumerate() {
+            let (branch, indent) = if index + 1 == count {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            let label = hub
+                .port_label(port)
+                .map(|l| format!("[{}]", l))
+                .unwrap_or_default();
+            let state = match status {
+                Ok(status) => self.port_state(&status),
+                Err(e) => self.paint(&format!("error {}", e), COLOR_RED),
+            };
+            let _ = write!(output, "{}{}{}{} {}", prefix, branch, port, label, state);
+            match device_info {
+                Some(info) if info.class() == DEVICE_CLASS_HUB && !self.filter.selects_hubs() => {
+                    let child = Hub::open(info, self.quirks, hub.timeout())?;
+                    let _ = writeln!(output, " {}", Self::hub_name(&child));
+                    self.ports(output, &child, &format!("{}{}", prefix, indent))?;
+                }
+                Some(info) => {
+                    let _ = writeln!(output, " {}", Self::device_name(info));
+                }
+                None => {
+                    let _ = writeln!(output);
+                }
             }
-        },
+        }
+        Ok(())
+    }
+}
+
+fn list_tree(
+    topology: &Topology,
+    quirks: &Quirks,
+    filter: &ListFilter,
+    color: bool,
+    timeout: Duration,
+) -> Result<(), Error> {
+    let tree = Tree {
+        topology,
+        quirks,
+        filter,
+        color,
+    };
+    let mut buffer = Vec::new();
+    if filter.selects_hubs() {
+        for hub in filter.hubs(topology, quirks, timeout) {
+            tree.hub(&mut buffer, &hub)?;
+        }
+    } else {
+        for (_, info) in topology.top_level_hubs() {
+            let hub = Hub::open(info, quirks, timeout)?;
+            tree.hub(&mut buffer, &hub)?;
+        }
+    }
+    print!("{}", String::from_utf8_lossy(&buffer));
+    Ok(())
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value { "yes" } else { "no" }
+}
+
+/// Decimal version from a BCD encoded version
+fn bcd_version(version: u16) -> String {
+    format!("{:x}.{:02x}", version >> 8, version & 0xff)
+}
+
+fn describe_capability(capability: &DeviceCapability) -> String {
+    match capability {
+        DeviceCapability::Usb20Extension(c) => {
+            format!(
+                "USB 2.0 extension, LPM {}, BESL {}",
+                yes_no(c.lpm()),
+                yes_no(c.besl())
+            )
+        }
+        DeviceCapability::SuperSpeedUsb(c) => format!(
+            "SuperSpeed USB, speeds {:#06x}, U1 exit latency {} us, U2 exit latency {} us, LTM {}",
+            c.speeds_supported(),
+            c.u1_exit_latency(),
+            c.u2_exit_latency(),
+            yes_no(c.ltm())
+        ),
+        DeviceCapability::SuperSpeedPlus(c) => {
+            let lane_speeds = c
+                .sublink_speed_attributes()
+                .iter()
+                .map(|a| format!("{} Mb/s", a.lane_speed() / 1_000_000))
+                .collect::<Vec<String>>();
+            format!(
+                "SuperSpeedPlus, {} rx and {} tx lanes, lane speeds {}",
+                c.min_rx_lanes(),
+                c.min_tx_lanes(),
+                lane_speeds.join(", ")
+            )
+        }
+        DeviceCapability::ContainerId(container_id) => format!("Container ID {}", container_id),
+        DeviceCapability::Platform(c) => format!(
+            "Platform {}, {} bytes",
+            ContainerId(*c.uuid()),
+            c.data().len()
+        ),
+        DeviceCapability::PowerDelivery(c) => format!(
+            "Power Delivery, battery charging {}, power delivery {}, provider {}, consumer {}",
+            yes_no(c.battery_charging()),
+            yes_no(c.power_delivery()),
+            yes_no(c.provider()),
+            yes_no(c.consumer())
+        ),
+        DeviceCapability::Billboard(c) => format!(
+            "Billboard {}, {} alternate modes",
+            bcd_version(c.version()),
+            c.alternate_modes().len()
+        ),
+        DeviceCapability::PrecisionTimeMeasurement => String::from("Precision Time Measurement"),
+        DeviceCapability::Unknown {
+            capability_type,
+            data,
+        } => format!(
+            "Unknown type {:#04x}, {} bytes",
+            capability_type,
+            data.len()
+        ),
+    }
+}
+
+/// Write the descriptors and the hub and port status of a hub
+fn describe<W: Write>(output: &mut W, hub: &Hub) {
+    let info = hub.info();
+    let descriptor = hub.hub_descriptor();
+
+    let _ = writeln!(output, "Hub {}", hub.location());
+    let _ = writeln!(output, "Device descriptor");
+    let _ = writeln!(
+        output,
+        "  Bus {:03} address {:03}",
+        info.busnum(),
+        info.device_address()
+    );
+    let _ = writeln!(
+        output,
+        "  VID:PID {:04x}:{:04x}",
+        info.vendor_id(),
+        info.product_id()
+    );
+    let _ = writeln!(
+        output,
+        "  Class {:02x} subclass {:02x} protocol {:02x}",
+        info.class(),
+        info.subclass(),
+        info.protocol()
+    );
+    let _ = writeln!(output, "  USB version {}", bcd_version(info.usb_version()));
+    let _ = writeln!(
+        output,
+        "  Device version {}",
+        bcd_version(info.device_version())
+    );
+    if let Some(speed) = DeviceSummary::from_device_info(&info).speed {
+        let _ = writeln!(output, "  Speed {}", speed);
+    }
+    for (name, value) in [
+        ("Manufacturer", info.manufacturer_string()),
+        ("Product", info.product_string()),
+        ("Serial number", info.serial_number()),
+    ] {
+        if let Some(value) = value {
+            let _ = writeln!(output, "  {} {}", name, value);
+        }
+    }
+
+    let _ = writeln!(
+        output,
+        "{}",
+        if descriptor.super_speed() {
+            "SuperSpeed hub descriptor"
+        } else {
+            "Hub descriptor"
+        }
+    );
+    let _ = writeln!(output, "  Ports {}", descriptor.port_count());
+    let _ = writeln!(
+        output,
+        "  Characteristics {:#06x}",
+        descriptor.characteristics()
+    );
+    let switching_mode = |mode| match mode {
+        LogicalPowerSwitchingMode::IndividualPort => "individual",
+        LogicalPowerSwitchingMode::Common => "ganged",
+        LogicalPowerSwitchingMode::None => "none",
+    };
+    let _ = writeln!(
+        output,
+        "  Power switching {}",
+        switching_mode(descriptor.logical_power_switching_mode())
+    );
+    if hub.power_switching_mode() != descriptor.logical_power_switching_mode() {
+        let _ = writeln!(
+            output,
+            "  Power switching from quirk {}",
+            switching_mode(hub.power_switching_mode())
+        );
+    }
+    let _ = writeln!(
+        output,
+        "  Over-current protection {}",
+        match descriptor.over_current_protection_mode() {
+            OverCurrentProtectionMode::IndividualPort => "individual",
+            OverCurrentProtectionMode::Global => "global",
+            OverCurrentProtectionMode::None => "none",
+        }
+    );
+    let _ = writeln!(
+        output,
+        "  Compound device {}",
+        yes_no(descriptor.compound_device())
+    );
+    if let Some(think_time) = descriptor.tt_think_time() {
+        let _ = writeln!(output, "  TT think time {} FS bit times", think_time);
+    }
+    let _ = writeln!(
+        output,
+        "  Port indicators {}",
+        yes_no(descriptor.port_indicators())
+    );
+    let _ = writeln!(
+        output,
+        "  Power on to power good {} ms",
+        descriptor.power_good_time().as_millis()
+    );
+    let _ = writeln!(
+        output,
+        "  Controller current {} mA",
+        descriptor.controller_current()
+    );
+    let removable = (1..=descriptor.port_count())
+        .filter(|port| descriptor.port_removable(*port) == Some(true))
+        .map(|port| port.to_string())
+        .collect::<Vec<String>>();
+    if descriptor.port_removable(1).is_some() {
+        let _ = writeln!(output, "  Removable ports {}", removable.join(","));
+    }
+
+    match hub.bos_descriptor() {
+        Some(bos) => {
+            let _ = writeln!(output, "BOS descriptor");
+            for capability in bos.capabilities() {
+                let _ = writeln!(output, "  {}", describe_capability(capability));
+            }
+        }
+        None => {
+            let _ = writeln!(output, "No BOS descriptor");
+        }
+    }
+    if let Some(container_id) = hub.container_id() {
+        let _ = writeln!(output, "Container ID {}", container_id);
+    }
+    if let Some(quirk) = hub.quirk() {
+        let _ = writeln!(output, "Quirks {}", quirk.names().join(","));
+    }
+
+    match hub.hub_status() {
+        Ok(status) => {
+            let _ = writeln!(
+                output,
+                "Hub status {:04x} change {:04x}, local power {}, over-current {}",
+                status.status,
+                status.change,
+                if status.local_power_lost() {
+                    "lost"
+                } else {
+                    "good"
+                },
+                yes_no(status.overcurrent())
+            );
+        }
+        Err(e) => {
+            let _ = writeln!(output, "Hub status failed, {}", e);
+        }
+    }
+
+    for port in 1..=hub.port_count() {
+        let label = hub
+            .port_label(port)
+            .map(|l| format!("[{}]", l))
+            .unwrap_or_default();
+        match hub.port_status_change(port) {
+            Ok((status, change)) => {
+                let flags = [
+                    (status.connection(), "connection"),
+                    (status.enabled(), "enabled"),
+                    (status.suspended(), "suspended"),
+                    (status.overcurrent(), "overcurrent"),
+                    (status.reset(), "reset"),
+                    (status.powered(), "powered"),
+                ]
+                .into_iter()
+                .filter(|(set, _)| *set)
+                .map(|(_, name)| name)
+                .collect::<Vec<&str>>();
+                let _ = writeln!(
+                    output,
+                    "Port {}{} status {:04x} {} change {:04x} {}",
+                    port,
+                    label,
+                    status.field(),
+                    flags.join(" "),
+                    change.field(),
+                    change.names().join(" ")
+                );
+            }
+            Err(e) => {
+                let _ = writeln!(output, "Port {}{} status failed, {}", port, label, e);
+            }
+        }
+    }
+}
+
+/// Read the state of a single hub port
+fn port_state(
+    port: &PortLocation,
+    topology: &Topology,
+    quirks: &Quirks,
+    timeout: Duration,
+) -> Result<PortState, String> {
+    let info = topology
+        .device(&port.hub)
+        .ok_or_else(|| format!("No device attached at {}", port.hub))?;
+    let hub = Hub::open(info, quirks, timeout).map_err(|e| e.to_string())?;
+    Ok(HubState::read_port(
+        &hub,
+        port.port,
+        topology.device(&port.device()),
+    ))
+}
+
+/// Port state together with the location of its hub
+#[derive(Serialize)]
+struct LocatedPortState<'a> {
+    hub: &'a Location,
+    #[serde(flatten)]
+    state: &'a PortState,
+}
+
+fn describe_port(port: &PortLocation, state: &PortState) {
+    let mut line = port.to_string();
+    if let Some(label) = &state.label {
+        line.push_str(&format!("[{}]", label));
+    }
+    if let Some(status) = &state.status {
+        line.push_str(&format!(" {:04x}", status.field()));
+        for (flag, name) in [
+            (status.connection(), "connection"),
+            (status.enabled(), "enabled"),
+            (status.suspended(), "suspended"),
+            (status.overcurrent(), "overcurrent"),
+            (status.reset(), "reset"),
+            (status.powered(), "powered"),
+        ] {
+            if flag {
+                line.push(' ');
+                line.push_str(name);
+            }
+        }
+    }
+    if let Some(device) = &state.device {
+        line.push_str(&format!(
+            " {:04x}:{:04x} {} {} {}",
+            device.vendor_id,
+            device.product_id,
+            device.manufacturer.as_deref().unwrap_or(""),
+            device.product.as_deref().unwrap_or(""),
+            device.serial_number.as_deref().unwrap_or("")
+        ));
+    }
+    println!("{}", line.trim_end());
+}
+
+/// Event with the time it was seen
+#[derive(Serialize)]
+struct TimedEvent<'a> {
+    /// Seconds since the Unix epoch
+    time: f64,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+/// Poll the hubs matching the filter and print the changes until interrupted
+///
+/// Hubs are opened once, and again when the device at the hub location changes address.
+fn monitor(
+    filter: &ListFilter,
+    quirks: &Quirks,
+    timeout: Duration,
+    interval: Duration,
+    format: Format,
+) -> Result<(), Error> {
+    let mut hubs: BTreeMap<Location, Hub> = BTreeMap::new();
+    let mut skipped: BTreeMap<Location, u8> = BTreeMap::new();
+    let mut previous: Option<Snapshot> = None;
+    loop {
+        let topology = Topology::enumerate()?;
+        let address = |location: &Location| topology.device(location).map(|d| d.device_address());
+        hubs.retain(|location, hub| address(location) == Some(hub.info().device_address()));
+        skipped.retain(|location, skipped| address(location) == Some(*skipped));
+        for (location, info) in topology.hubs() {
+            if hubs.contains_key(location)
+                || skipped.contains_key(location)
+                || !filter.matches_hub(location, info.vendor_id(), info.product_id())
+            {
+                continue;
+            }
+            match Hub::open(info, quirks, timeout) {
+                Ok(hub) if filter.matches_opened_hub(&hub) => {
+                    hubs.insert(location.clone(), hub);
+                }
+                Ok(_) => {
+                    skipped.insert(location.clone(), info.device_address());
+                }
+                Err(e) => {
+                    eprintln!("Skipping hub {}, {}", location, e);
+                    skipped.insert(location.clone(), info.device_address());
+                }
+            }
+        }
+
+        let snapshot = Snapshot::read(hubs.values());
+        if let Some(previous) = &previous {
+            let time = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64();
+            for event in previous.changes(&snapshot) {
+                match format {
+                    Format::Text => println!("{:.3} {}", time, event),
+                    _ => println!(
+                        "{}",
+                        serde_json::to_string(&TimedEvent {
+                            time,
+                            event: &event
+                        })
+                        .expect("serialize to JSON")
+                    ),
+                }
+            }
+        }
+        previous = Some(snapshot);
+        std::thread::sleep(interval);
+    }
+}
+
+/// Outcome of switching off a hub port with a device attached
+struct ProbeResult {
+    /// Port reported a connection after power was switched off
+    connection: bool,
+    /// Device was still enumerated after power was switched off
+    enumerated: bool,
+    /// Devices on other ports which disappeared as well
+    others: Vec<Location>,
+    /// Device enumerated again after power was restored
+    restored: bool,
+}
+
+impl ProbeResult {
+    fn vbus_switched(&self) -> bool {
+        !self.connection && !self.enumerated
+    }
+}
+
+/// Quirks for probing a hub, keeping only the quirk parts which do not change the claims of its
+/// hub descriptor
+fn probe_quirks(info: &DeviceInfo, quirks: &Quirks) -> Quirks {
+    let mut probe_quirks = Quirks::default();
+    if let Some(quirk) = quirks.lookup(info.vendor_id(), info.product_id(), info.device_version()) {
+        probe_quirks.insert(Quirk {
+            ganged: false,
+            no_vbus_ports: Vec::new(),
+            companion_required: false,
+            ..quirk
+        });
+    }
+    probe_quirks
+}
+
+fn probe(
+    location: &Location,
+    port: u8,
+    settle: Duration,
+    timeout: Duration,
+    topology: &Topology,
+    quirks: &Quirks,
+) -> Result<ProbeResult, Error> {
+    let info = topology.device(location).ok_or_else(|| Error::NoDevice {
+        location: location.clone(),
+    })?;
+    // Test the claim of the hub descriptor, not the claim of the quirk
+    let hub = Hub::open(info, &probe_quirks(info, quirks), timeout)?;
+
+    let device_location = location.child(port);
+    if !hub.port_status(port)?.connection() || topology.device(&device_location).is_none() {
+        return Err(Error::NoDevice {
+            location: device_location,
+        });
+    }
+    let others = topology
+        .ports(location)
+        .filter(|(p, _)| *p != port)
+        .map(|(p, _)| location.child(p))
+        .collect::<Vec<Location>>();
+
+    hub.set_port_power(port, false)?;
+    std::thread::sleep(settle);
+    let status = hub.port_status(port);
+    let off_topology = Topology::enumerate();
+    // Always try to restore power before reporting errors
+    hub.set_port_power(port, true)?;
+    let status = status?;
+    let off_topology = off_topology?;
+
+    std::thread::sleep(settle);
+    let restored = Topology::enumerate()?.device(&device_location).is_some();
+
+    Ok(ProbeResult {
+        connection: status.connection(),
+        enumerated: off_topology.device(&device_location).is_some(),
+        others: others
+            .into_iter()
+            .filter(|l| off_topology.device(l).is_none())
+            .collect(),
+        restored,
+    })
+}
+
+/// Record the probe result of a hub port in the local quirks file
+fn write_probe_quirk(
+    path: &Path,
+    vendor_id: u16,
+    product_id: u16,
+    port: u8,
+    result: &ProbeResult,
+) -> Result<(), Error> {
+    let mut quirks = if path.exists() {
+        Quirks::load(path)?
+    } else {
+        Quirks::default()
+    };
+    let quirk = quirks.entry(vendor_id, product_id);
+    quirk.no_vbus_ports.retain(|p| *p != port);
+    if !result.vbus_switched() {
+        quirk.no_vbus_ports.push(port);
+        quirk.no_vbus_ports.sort();
+    }
+    if !result.others.is_empty() {
+        quirk.ganged = true;
+    }
+    quirks.save(path)
+}
+
+/// Simple program to greet a person
+#[derive(clap::Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    /// Root of the sysfs file system
+    #[arg(long, global = true, default_value = SYSFS_ROOT)]
+    sysfs_root: PathBuf,
+
+    /// Local hub quirks file, extending the built-in quirks [default: ~/.config/uhubctrl/quirks.toml]
+    #[arg(long, global = true)]
+    quirks: Option<PathBuf>,
+
+    /// Port aliases file [default: ~/.config/uhubctrl/aliases.toml]
+    #[arg(long, global = true)]
+    aliases: Option<PathBuf>,
+
+    /// Configuration file, read after /etc/uhubctrl/config.toml and ~/.config/uhubctrl/config.toml
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Timeout of control requests, in milliseconds [default: 5000]
+    #[arg(long, global = true)]
+    timeout: Option<u64>,
+
+    /// Output format [default: text]
+    #[arg(long, global = true, value_enum)]
+    format: Option<Format>,
+
+    /// Switch off protected ports
+    #[arg(long, global = true)]
+    force: bool,
+
+    /// Log the USB requests and sysfs writes of mutating commands instead of issuing them
+    #[arg(long, global = true)]
+    dry_run: bool,
+}
+
+/// Output format
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum Format {
+    /// Human readable text
+    Text,
+    /// JSON document
+    Json,
+    /// One JSON object per line
+    JsonLines,
+}
+
+/// Output format from the command line, or from the configuration
+fn output_format(format: Option<Format>, config: &Config) -> Result<Format, String> {
+    match (format, &config.format) {
+        (Some(format), _) => Ok(format),
+        (None, Some(format)) => <Format as clap::ValueEnum>::from_str(format, true)
+            .map_err(|_| format!("Unknown output format {}", format)),
+        (None, None) => Ok(Format::Text),
+    }
+}
+
+/// Default time to wait for devices to disconnect and reconnect
+const DEFAULT_SETTLE: Duration = Duration::from_secs(2);
+
+/// Path of the system configuration file
+const SYSTEM_CONFIG_PATH: &str = "/etc/uhubctrl/config.toml";
+
+/// Directory of the user configuration
+fn config_dir() -> Option<PathBuf> {
+    let config = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(config) => PathBuf::from(config),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config.join("uhubctrl"))
+}
+
+/// Default path of the local quirks file
+fn default_quirks_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("quirks.toml"))
+}
+
+/// Load the system and user configuration files if they exist, followed by the given file
+///
+/// Returns the configuration and the paths of the files read.
+fn load_config(path: Option<&Path>) -> Result<(Config, Vec<PathBuf>), Error> {
+    let mut config = Config::default();
+    let mut paths = Vec::new();
+    let defaults = [
+        Some(PathBuf::from(SYSTEM_CONFIG_PATH)),
+        config_dir().map(|dir| dir.join("config.toml")),
+    ];
+    for default in defaults.into_iter().flatten() {
+        if default.exists() {
+            config.merge(Config::load(&default)?);
+            paths.push(default);
+        }
+    }
+    if let Some(path) = path {
+        config.merge(Config::load(path)?);
+        paths.push(path.to_path_buf());
+    }
+    Ok((config, paths))
+}
+
+/// Default path of the port aliases file
+fn default_aliases_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("aliases.toml"))
+}
+
+/// Load the port aliases file
+fn load_aliases(path: Option<&Path>) -> Result<Aliases, Error> {
+    match path {
+        Some(path) => Aliases::load(path),
+        None => match default_aliases_path() {
+            Some(path) if path.exists() => Aliases::load(path),
+            _ => Ok(Aliases::default()),
+        },
+    }
+}
+
+/// Resolve the hub given as location, without location the hub from the configuration is used
+fn resolve_hub(location: Option<&str>, config: &Config) -> Result<Location, String> {
+    match location {
+        Some(location) => location.parse::<Location>().map_err(|e| e.to_string()),
+        None => config.hub.clone().ok_or_else(|| {
+            String::from("No hub given, use --location or set hub in the configuration")
+        }),
+    }
+}
+
+/// Resolve the port given as hub location and port, `<hub location>:<port>` or port alias
+fn resolve_port(
+    location: Option<&str>,
+    port: Option<u8>,
+    config: &Config,
+) -> Result<PortAlias, String> {
+    match (location, port) {
+        (_, Some(port)) => Ok(PortAlias::new(PortLocation::new(
+            resolve_hub(location, config)?,
+            port,
+        ))),
+        (Some(name), None) if name.parse::<Location>().is_err() => {
+            config.aliases.resolve(name).map_err(|e| e.to_string())
+        }
+        (_, None) => Err(format!(
+            "No port given for hub {}",
+            resolve_hub(location, config)?
+        )),
+    }
+}
+
+/// Resolve the hubs and ports given as hub location and port list, or as a single port
+fn resolve_ports(
+    location: Option<&str>,
+    ports: Option<&PortList>,
+    config: &Config,
+) -> Result<(Vec<Location>, PortList), String> {
+    match ports {
+        Some(ports) => Ok((vec![resolve_hub(location, config)?], ports.clone())),
+        None => {
+            let alias = resolve_port(location, None, config)?;
+            let hubs = alias.hubs().cloned().collect();
+            Ok((hubs, PortList::Ports(vec![alias.port.port])))
+        }
+    }
+}
+
+/// Switch a hub port and report the result, returns `false` on failure
+fn power_port(
+    hub: &Hub,
+    port: u8,
+    on: bool,
+    backend: PortControlBackend,
+    config: &Config,
+    force: bool,
+) -> bool {
+    let result = if on {
+        Ok(())
+    } else {
+        check_protected(config, &PortLocation::new(hub.location(), port), force)
+    }
+    .and_then(|_| {
+        hub.switch_port(port, on, backend)
+            .map_err(|e| format!("Failed to switch port, {}", e))
+    });
+    let info = hub.info();
+    println!(
+        "PORT {} {} LOCATION {} {:02x} {:02x} {}",
+        port,
+        if on { "on" } else { "off" },
+        hub.location(),
+        info.busnum(),
+        info.device_address(),
+        if result.is_ok() { "ok" } else { "failed" }
+    );
+    match result {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("{}", e);
+            false
+        }
+    }
+}
+
+/// Fail if a port is protected and switching protected ports is not forced
+fn check_protected(config: &Config, port: &PortLocation, force: bool) -> Result<(), String> {
+    if !force && config.is_protected(port) {
+        Err(format!("Port {} is protected, use --force", port))
+    } else {
+        Ok(())
+    }
+}
+
+/// Check the configuration against the current topology, returns the problems found
+fn check_config(config: &Config, topology: &Topology, sysfs: &Sysfs) -> Vec<String> {
+    let check_hub = |what: &str, hub: &Location, port: Option<u8>| match topology.device(hub) {
+        None => Some(format!("{}, no device at {}", what, hub)),
+        Some(info) if info.class() != DEVICE_CLASS_HUB => {
+            Some(format!("{}, device at {} is not a hub", what, hub))
+        }
+        Some(_) => match (port, sysfs.hub(hub)) {
+            (Some(port), Ok(sysfs_hub)) if port > sysfs_hub.port_count() => Some(format!(
+                "{}, hub {} has {} ports",
+                what,
+                hub,
+                sysfs_hub.port_count()
+            )),
+            _ => None,
+        },
+    };
+    let mut problems = Vec::new();
+    if let Some(hub) = &config.hub {
+        problems.extend(check_hub("Default hub", hub, None));
+    }
+    for (name, alias) in config.aliases.iter() {
+        let what = format!("Alias {}", name);
+        for hub in alias.hubs() {
+            problems.extend(check_hub(&what, hub, Some(alias.port.port)));
+        }
+    }
+    for name in config.protected.iter() {
+        let what = format!("Protected port {}", name);
+        match config.aliases.resolve(name) {
+            Ok(alias) => {
+                for hub in alias.hubs() {
+                    problems.extend(check_hub(&what, hub, Some(alias.port.port)));
+                }
+            }
+            Err(e) => problems.push(format!("{}, {}", what, e)),
+        }
+    }
+    if let Err(e) = output_format(None, config) {
+        problems.push(e);
+    }
+    problems
+}
+
+/// Load the built-in quirks, extended with the configuration quirks and the local quirks file
+fn load_quirks(path: Option<&Path>, config: &Config) -> Result<Quirks, Error> {
+    let mut quirks = Quirks::builtin();
+    quirks.extend(config.quirks.clone());
+    match path {
+        Some(path) => quirks.extend(Quirks::load(path)?),
+        None => {
+            if let Some(path) = default_quirks_path()
+                && path.exists()
+            {
+                quirks.extend(Quirks::load(path)?);
+            }
+        }
+    }
+    Ok(quirks)
+}
+
+/// Method used to switch hub ports
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum Backend {
+    /// Hub class port power requests over USB
+    Usb,
+    /// Linux sysfs port disable attribute
+    Sysfs,
+    /// USB, falling back to sysfs when the hub does not support port power switching
+    Auto,
+}
+
+impl From<Backend> for PortControlBackend {
+    fn from(backend: Backend) -> Self {
+        match backend {
+            Backend::Usb => PortControlBackend::Usb,
+            Backend::Sysfs => PortControlBackend::Sysfs,
+            Backend::Auto => PortControlBackend::Auto,
+        }
+    }
+}
+
+/// Action of the power command
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum PowerAction {
+    /// Switch on
+    On,
+    /// Switch off
+    Off,
+    /// Switch off if on, on if off
+    Toggle,
+    /// Switch off, wait and switch on again
+    Cycle,
+}
+
+/// Port state checked by the status command
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum PortCheck {
+    /// Port is powered
+    Powered,
+    /// A device is connected to the port
+    Connected,
+    /// Port is enabled
+    Enabled,
+}
+
+impl PortCheck {
+    fn matches(&self, status: &PortStatus) -> bool {
+        match self {
+            Self::Powered => status.powered(),
+            Self::Connected => status.connection(),
+            Self::Enabled => status.enabled(),
+        }
+    }
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Commands {
+    List {
+        /// Read hub and port status from sysfs without opening the hubs, no privileges required
+        #[arg(short, long)]
+        read_only: bool,
+
+        /// Show the hubs and ports as a tree
+        #[arg(short, long, conflicts_with = "read_only")]
+        tree: bool,
+
+        /// Color the port state in the tree
+        #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+        color: ColorChoice,
+
+        #[command(flatten)]
+        filter: ListFilter,
+    },
+    Power {
+        /// Action applied to the ports
+        #[arg(value_enum)]
+        action: PowerAction,
+
+        /// Ports, as a list of ports and port ranges such as 1,3-5, or all
+        #[arg(short, long)]
+        port: Option<PortList>,
+
+        /// Time between switching off and on when cycling, in milliseconds [default: 2000]
+        #[arg(short, long)]
+        delay: Option<u64>,
+
+        /// Hub location, `<hub location>:<port>` or port alias
+        #[arg(short, long)]
+        location: Option<String>,
+
+        /// Select hub by container id, applies to all hubs sharing the id such as USB 3 companions
+        #[arg(short, long, conflicts_with = "location")]
+        container_id: Option<String>,
+
+        #[command(flatten)]
+        selector: HubSelector,
+
+        /// Select the hub port by the attached device, including the peer port of the companion
+        /// hub sharing the connector
+        #[command(flatten)]
+        device: DeviceArgs,
+
+        /// Method used to switch the port
+        #[arg(short, long, value_enum, default_value_t = Backend::Usb)]
+        backend: Backend,
+    },
+    /// Deauthorize the device on a hub port, unbinding its drivers without cutting power
+    Unbind {
+        #[arg(short, long)]
+        port: Option<u8>,
+
+        /// Hub location, `<hub location>:<port>` or port alias
+        #[arg(short, long)]
+        location: Option<String>,
+    },
+    /// Reauthorize the device on a hub port, binding its drivers again
+    Rebind {
+        #[arg(short, long)]
+        port: Option<u8>,
+
+        /// Hub location, `<hub location>:<port>` or port alias
+        #[arg(short, long)]
+        location: Option<String>,
+    },
+    /// Show the status of a single port
+    ///
+    /// Exits with 1 if the check fails and with 2 if the status cannot be read.
+    Status {
+        /// Hub location, `<hub location>:<port>` or port alias
+        location: Option<String>,
+
+        #[arg(short, long)]
+        port: Option<u8>,
+
+        /// Exit with 1 unless the port is in the given state
+        #[arg(short, long, value_enum)]
+        check: Option<PortCheck>,
+
+        /// Do not print the status
+        #[arg(short, long)]
+        quiet: bool,
+    },
+    /// Find the hub ports of devices
+    Find {
+        #[command(flatten)]
+        device: DeviceArgs,
+    },
+    /// Show everything known about a hub, its descriptors and the status of all ports
+    Describe {
+        /// Hub location
+        location: Option<String>,
+    },
+    /// Print hub and port events until interrupted
+    ///
+    /// Events are written as JSON lines with either of the JSON output formats.
+    Monitor {
+        /// Only hubs at or below the location
+        #[arg(short, long)]
+        location: Option<Location>,
+
+        /// Only hubs with the given VID:PID, can be repeated
+        #[arg(long = "vid-pid")]
+        vid_pids: Vec<VidPid>,
+
+        /// Only hubs with individual port power switching
+        #[arg(short, long)]
+        switchable: bool,
+
+        /// Time between reading the port status, in milliseconds
+        #[arg(short, long, default_value_t = 500)]
+        interval: u64,
+    },
+    /// List the port aliases
+    Aliases,
+    /// Switch off a port with a device attached to find out if the hub really cuts VBUS
+    Probe {
+        /// Hub location, `<hub location>:<port>` or port alias
+        location: Option<String>,
+
+        #[arg(short, long)]
+        port: Option<u8>,
+
+        /// Time to wait for the device to disappear and reappear, in milliseconds [default: 2000]
+        #[arg(short, long)]
+        settle: Option<u64>,
+
+        /// Record the result in the local quirks file
+        #[arg(short, long)]
+        write_quirks: bool,
+    },
+    /// Generate udev rules granting a group access to the hubs
+    UdevRules {
+        /// Group granted access
+        #[arg(short, long, default_value = "plugdev")]
+        group: String,
+
+        /// Permissions of the device nodes
+        #[arg(short, long, default_value = "0660")]
+        mode: String,
+
+        /// Only include hubs with the given VID:PID, can be repeated
+        #[arg(long = "vid-pid")]
+        vid_pids: Vec<VidPid>,
+
+        /// Only include hubs at the given location, can be repeated
+        #[arg(short, long = "location")]
+        locations: Vec<String>,
+
+        /// Also grant write access to the sysfs port disable attributes
+        #[arg(short, long)]
+        sysfs: bool,
+    },
+    /// Configuration file commands
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum ConfigCommands {
+    /// Validate the configuration against the current topology
+    Check,
+}
+
+fn main() {
+    let args = Args::parse();
+    let mut logger =
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("error"));
+    if args.dry_run {
+        // Dry runs are reported through the library log, which must be shown even with RUST_LOG
+        logger.filter_module("usb_hub_control", log::LevelFilter::Info);
+    }
+    logger.init();
+
+    let (mut config, config_paths) = match load_config(args.config.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let quirks = match load_quirks(args.quirks.as_deref(), &config) {
+        Ok(quirks) => quirks,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    match load_aliases(args.aliases.as_deref()) {
+        Ok(aliases) => config.aliases.extend(aliases),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+    let aliases = &config.aliases;
+
+    let timeout = args
+        .timeout
+        .map(Duration::from_millis)
+        .or(config.timeout)
+        .unwrap_or(DEFAULT_TIMEOUT);
+
+    let topology = match Topology::enumerate() {
+        Ok(topology) => topology,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    match args.command {
+        Some(Commands::Power {
+            action,
+            port,
+            delay,
+            location,
+            container_id,
+            selector,
+            device,
+            backend,
+        }) => {
+            let device = device.selector();
+            let hubs: Vec<(Hub, PortList)> = if !device.is_empty() {
+                if location.is_some() || port.is_some() || container_id.is_some() {
+                    eprintln!("A device cannot be combined with a location, port or container id");
+                    std::process::exit(1);
+                }
+                if !selector.is_empty() {
+                    eprintln!("A device cannot be combined with a hub selector");
+                    std::process::exit(1);
+                }
+                match device_port(&device, &topology, &quirks, timeout) {
+                    Ok((hub, port)) => vec![(hub, PortList::Ports(vec![port]))],
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                }
+            } else if let Some(container_id) = container_id {
+                let Some(ports) = port else {
+                    eprintln!("No port given for container id {}", container_id);
+                    std::process::exit(1);
+                };
+                let hubs = match container_id.parse::<ContainerId>() {
+                    Ok(container_id) => {
+                        let hubs =
+                            hubs_with_container_id(&topology, &container_id, &quirks, timeout);
+                        if hubs.is_empty() {
+                            eprintln!("No hub with container id {}", container_id);
+                            std::process::exit(1);
+                        }
+                        hubs
+                    }
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                };
+                hubs.into_iter().map(|hub| (hub, ports.clone())).collect()
+            } else if !selector.is_empty() || (location.is_none() && config.hub.is_none()) {
+                let Some(ports) = port else {
+                    eprintln!("No port given, use --port or a port alias");
+                    std::process::exit(1);
+                };
+                match selector.select(&topology, &quirks, timeout) {
+                    Ok(hub) => vec![(hub, ports)],
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                }
+            } else {
+                let (locations, ports) =
+                    match resolve_ports(location.as_deref(), port.as_ref(), &config) {
+                        Ok(resolved) => resolved,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            std::process::exit(1);
+                        }
+                    };
+                let hubs = locations
+                    .iter()
+                    .map(|l| {
+                        topology
+                            .device(l)
+                            .ok_or_else(|| format!("No hub at {}", l))
+                            .and_then(|info| {
+                                Hub::open(info, &quirks, timeout).map_err(|e| e.to_string())
+                            })
+                    })
+                    .collect::<Result<Vec<Hub>, String>>();
+                let hubs = match hubs {
+                    Ok(hubs) => hubs,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                };
+                hubs.into_iter().map(|hub| (hub, ports.clone())).collect()
+            };
+            let backend = PortControlBackend::from(backend);
+            let mut targets = Vec::new();
+            for (mut hub, ports) in hubs {
+                hub.set_sysfs(Sysfs::new(&args.sysfs_root));
+                hub.set_dry_run(args.dry_run);
+                // The peer port of a device's hub port is switched too, so that the device loses
+                // power on both the USB 2 and the USB 3 hub
+                if (!device.is_empty() || hub.companion_required())
+                    && let Err(e) = hub.attach_companion(&topology, &quirks)
+                {
+                    eprintln!(
+                        "Failed to open the companion hub of {}, {}",
+                        hub.location(),
+                        e
+                    );
+                    std::process::exit(1);
+                }
+                let hub_ports = ports.ports(hub.port_count());
+                targets.push((hub, hub_ports));
+            }
+            let switch = |hub: &Hub, port: u8, on: bool| {
+                power_port(hub, port, on, backend, &config, args.force)
+            };
+            let mut failed = false;
+            match action {
+                PowerAction::Cycle => {
+                    let mut switched_off = Vec::new();
+                    for (hub, ports) in targets.iter() {
+                        for port in ports.iter().copied() {
+                            if switch(hub, port, false) {
+                                switched_off.push((hub, port));
+                            } else {
+                                failed = true;
+                            }
+                        }
+                    }
+                    if !switched_off.is_empty() && !args.dry_run {
+                        std::thread::sleep(
+                            delay
+                                .map(Duration::from_millis)
+                                .or(config.settle)
+                                .unwrap_or(DEFAULT_SETTLE),
+                        );
+                    }
+                    for (hub, port) in switched_off {
+                        failed |= !switch(hub, port, true);
+                    }
+                }
+                action => {
+                    for (hub, ports) in targets.iter() {
+                        for port in ports.iter().copied() {
+                            let on = match action {
+                                PowerAction::On => true,
+                                PowerAction::Toggle => match hub.port_switched_on(port, backend) {
+                                    Ok(on) => !on,
+                                    Err(e) => {
+                                        eprintln!("Failed to read port {} state, {}", port, e);
+                                        failed = true;
+                                        continue;
+                                    }
+                                },
+                                _ => false,
+                            };
+                            failed |= !switch(hub, port, on);
+                        }
+                    }
+                }
+            }
+            if failed {
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Unbind { port, location }) => {
+            match resolve_port(location.as_deref(), port, &config)
+                .and_then(|alias| check_protected(&config, &alias.port, args.force).map(|_| alias))
+            {
+                Ok(alias) => {
+                    if let Err(e) = authorize(&args.sysfs_root, &alias, false, args.dry_run) {
+                        eprintln!("Failed to unbind, {}", e);
+                        std::process::exit(1);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Commands::Rebind { port, location }) => {
+            match resolve_port(location.as_deref(), port, &config) {
+                Ok(alias) => {
+                    if let Err(e) = authorize(&args.sysfs_root, &alias, true, args.dry_run) {
+                        eprintln!("Failed to rebind, {}", e);
+                        std::process::exit(1);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Commands::Status {
+            location,
+            port,
+            check,
+            quiet,
+        }) => {
+            let format = output_format(args.format, &config);
+            let state = resolve_port(location.as_deref(), port, &config).and_then(|alias| {
+                let state = port_state(&alias.port, &topology, &quirks, timeout)?;
+                Ok((alias.port, state, format?))
+            });
+            let (port, state, format) = match state {
+                Ok(state) => state,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(2);
+                }
+            };
+            if !quiet {
+                match format {
+                    Format::Text => describe_port(&port, &state),
+                    format => write_json(
+                        "ports",
+                        &[LocatedPortState {
+                            hub: &port.hub,
+                            state: &state,
+                        }],
+                        format,
+                    ),
+                }
+            }
+            let status = match (&state.status, &state.error) {
+                (Some(status), _) => status,
+                (None, error) => {
+                    eprintln!("Port status failed, {}", error.as_deref().unwrap_or(""));
+                    std::process::exit(2);
+                }
+            };
+            if check.is_some_and(|check| !check.matches(status)) {
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Find { device }) => {
+            let selector = device.selector();
+            if selector.is_empty() {
+                eprintln!(
+                    "No device given, use --device, --device-serial, --device-address or \
+                     --device-product"
+                );
+                std::process::exit(1);
+            }
+            let format = match output_format(args.format, &config) {
+                Ok(format) => format,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+            let found = topology.find(&selector);
+            match format {
+                Format::Text => {
+                    for found in found.iter() {
+                        println!("{}", describe_found_device(found));
+                    }
+                }
+                format => write_json("devices", &found, format),
+            }
+            if found.is_empty() {
+                eprintln!("No matching device found");
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Describe { location }) => {
+            let hub = resolve_hub(location.as_deref(), &config).and_then(|location| {
+                let info = topology
+                    .device(&location)
+                    .ok_or_else(|| format!("No hub at {}", location))?;
+                Hub::open(info, &quirks, timeout).map_err(|e| e.to_string())
+            });
+            match hub {
+                Ok(hub) => {
+                    let mut buffer = Vec::new();
+                    describe(&mut buffer, &hub);
+                    print!("{}", String::from_utf8_lossy(&buffer));
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Commands::Monitor {
+            location,
+            vid_pids,
+            switchable,
+            interval,
+        }) => {
+            let filter = ListFilter {
+                location,
+                vid_pids,
+                switchable,
+                ..Default::default()
+            };
+            let result = output_format(args.format, &config).and_then(|format| {
+                let interval = Duration::from_millis(interval);
+                monitor(&filter, &quirks, timeout, interval, format).map_err(|e| e.to_string())
+            });
+            if let Err(e) = result {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Aliases) => {
+            for (name, alias) in aliases.iter() {
+                match &alias.companion {
+                    Some(companion) => println!("{} {} {}", name, alias.port, companion),
+                    None => println!("{} {}", name, alias.port),
+                }
+            }
+        }
+        Some(Commands::Probe {
+            location,
+            port,
+            settle,
+            write_quirks,
+        }) => {
+            let PortLocation {
+                hub: location,
+                port,
+            } = match resolve_port(location.as_deref(), port, &config)
+                .and_then(|alias| check_protected(&config, &alias.port, args.force).map(|_| alias))
+            {
+                Ok(alias) => alias.port,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+            let settle = settle
+                .map(Duration::from_millis)
+                .or(config.settle)
+                .unwrap_or(DEFAULT_SETTLE);
+            if args.dry_run {
+                // Nothing can be measured, only show the requests switching the port off and on
+                let result = topology
+                    .device(&location)
+                    .ok_or_else(|| Error::NoDevice {
+                        location: location.clone(),
+                    })
+                    .and_then(|info| Hub::open(info, &probe_quirks(info, &quirks), timeout))
+                    .and_then(|mut hub| {
+                        hub.set_dry_run(true);
+                        hub.set_port_power(port, false)?;
+                        hub.set_port_power(port, true)
+                    });
+                if let Err(e) = result {
+                    eprintln!("Probe failed, {}", e);
+                    std::process::exit(1);
+                }
+                return;
+            }
+            let result = match probe(&location, port, settle, timeout, &topology, &quirks) {
+                Ok(result) => result,
+                Err(e) => {
+                    eprintln!("Probe failed, {}", e);
+                    std::process::exit(1);
+                }
+            };
+            println!(
+                "PORT {} LOCATION {} connection {} enumerated {}",
+                port,
+                location,
+                if result.connection { "yes" } else { "no" },
+                if result.enumerated { "yes" } else { "no" },
+            );
+            for other in result.others.iter() {
+                println!(
+                    "Device {} on another port disappeared, ports are ganged",
+                    other
+                );
+            }
+            if !result.restored {
+                eprintln!(
+                    "Device {} did not return after power was restored",
+                    location.child(port)
+                );
+            }
+            if result.vbus_switched() {
+                println!("Port {} switches VBUS", port);
+            } else {
+                println!("Port {} does not switch VBUS", port);
+            }
+            if write_quirks {
+                let info = topology
+                    .device(&location)
+                    .expect("probed hub is enumerated");
+                let path = match args.quirks.clone().or_else(default_quirks_path) {
+                    Some(path) => path,
+                    None => {
+                        eprintln!("No local quirks file, use --quirks");
+                        std::process::exit(1);
+                    }
+                };
+                match write_probe_quirk(&path, info.vendor_id(), info.product_id(), port, &result) {
+                    Ok(()) => println!("Quirk written to {}", path.display()),
+                    Err(e) => {
+                        eprintln!("Failed to write quirk, {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            if !result.vbus_switched() {
+                std::process::exit(2);
+            }
+        }
+        Some(Commands::UdevRules {
+            group,
+            mode,
+            vid_pids,
+            locations,
+            sysfs,
+        }) => {
+            let locations = match locations
+                .iter()
+                .map(|l| l.parse::<Location>())
+                .collect::<Result<Vec<Location>, Error>>()
+            {
+                Ok(locations) => locations,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+            print!(
+                "{}",
+                udev_rules(&topology, &group, &mode, &vid_pids, &locations, sysfs)
+            );
+        }
+        Some(Commands::Config {
+            command: ConfigCommands::Check,
+        }) => {
+            for path in config_paths.iter() {
+                println!("Read {}", path.display());
+            }
+            let problems = check_config(&config, &topology, &Sysfs::new(&args.sysfs_root));
+            for problem in problems.iter() {
+                println!("{}", problem);
+            }
+            if problems.is_empty() {
+                println!("Configuration OK");
+            } else {
+                std::process::exit(1);
+            }
+        }
+        command @ (Some(Commands::List { .. }) | None) => {
+            let (read_only, tree, color, filter) = match command {
+                Some(Commands::List {
+                    read_only,
+                    tree,
+                    color,
+                    filter,
+                }) => (read_only, tree, color, filter),
+                _ => (false, false, ColorChoice::Auto, ListFilter::default()),
+            };
+            let format = match output_format(args.format, &config) {
+                Ok(format) => format,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+            let sysfs = Sysfs::new(&args.sysfs_root);
+            if tree && format != Format::Text {
+                eprintln!("The tree is only available as text");
+                std::process::exit(1);
+            }
+            let result = match (read_only, format) {
+                (false, Format::Text) if tree => {
+                    list_tree(&topology, &quirks, &filter, color.enabled(), timeout)
+                }
+                (true, Format::Text) => list_sysfs(&sysfs, &filter),
+                (true, format) => list_sysfs_json(&sysfs, &quirks, &filter, format),
+                (false, Format::Text) => list(&topology, &quirks, &filter, timeout),
+                (false, format) => {
+                    list_json(&topology, &quirks, &filter, format, timeout);
+                    Ok(())
+                }
+            };
+            if let Err(e) = result {
+                eprintln!("List failed, {}", e);
+            }
+        }
     }
 }
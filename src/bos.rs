@@ -0,0 +1,736 @@
+use std::str::FromStr;
+
+use log::trace;
+use serde::{Serialize, Serializer};
+
+use crate::topology::parse_digits;
+use crate::{Error, ParseError};
+
+/// Device capability type, `bDevCapabilityType` of a device capability descriptor
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DeviceCapabilityType {
+    /// Wireless USB
+    WirelessUsb,
+    /// USB 2.0 Extension
+    Usb20Extension,
+    /// SuperSpeed USB
+    SuperSpeedUsb,
+    /// Container ID
+    ContainerId,
+    /// Platform
+    Platform,
+    /// Power Delivery
+    PowerDeliveryCapability,
+    /// Battery Info
+    BatteryInfoCapability,
+    /// Power Delivery consumer port
+    PowerDeliveryConsumerPortCapability,
+    /// Power Delivery provider port
+    PowerDeliveryProviderPortCapability,
+    /// SuperSpeedPlus
+    SuperSpeedPlus,
+    /// Precision Time Measurement
+    PrecisionTimeMeasurement,
+    /// Wireless USB extensions
+    WirelessUsbExtensions,
+    /// Billboard
+    Billboard,
+    /// Authentication
+    Authentication,
+    /// Billboard extensions
+    BillboardExtensions,
+    /// Configuration summary
+    ConfigurationSummary,
+    /// Firmware status
+    FwStatus,
+    /// Reserved
+    Reserved,
+}
+
+impl From<u8> for DeviceCapabilityType {
+    fn from(value: u8) -> Self {
+        match value {
+            0x01 => Self::WirelessUsb,
+            0x02 => Self::Usb20Extension,
+            0x03 => Self::SuperSpeedUsb,
+            0x04 => Self::ContainerId,
+            0x05 => Self::Platform,
+            0x06 => Self::PowerDeliveryCapability,
+            0x07 => Self::BatteryInfoCapability,
+            0x08 => Self::PowerDeliveryConsumerPortCapability,
+            0x09 => Self::PowerDeliveryProviderPortCapability,
+            0x0a => Self::SuperSpeedPlus,
+            0x0b => Self::PrecisionTimeMeasurement,
+            0x0c => Self::WirelessUsbExtensions,
+            0x0d => Self::Billboard,
+            0x0e => Self::Authentication,
+            0x0f => Self::BillboardExtensions,
+            0x10 => Self::ConfigurationSummary,
+            0x11 => Self::FwStatus,
+            _ => Self::Reserved,
+        }
+    }
+}
+
+/// Container Id
+///
+/// Formatted and parsed as a UUID in canonical form, `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx`.
+/// The first three UUID fields are stored little-endian in the descriptor, the same way `lsusb`
+/// presents them.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct ContainerId(pub [u8; 16]);
+
+impl ContainerId {
+    /// Order in which the descriptor bytes are presented in the canonical form
+    const CANONICAL_ORDER: [usize; 16] = [3, 2, 1, 0, 5, 4, 7, 6, 8, 9, 10, 11, 12, 13, 14, 15];
+}
+
+impl Serialize for ContainerId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl std::fmt::Display for ContainerId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (n, index) in Self::CANONICAL_ORDER.iter().enumerate() {
+            if n == 4 || n == 6 || n == 8 || n == 10 {
+                write!(f, "-")?;
+            }
+            write!(f, "{:02x}", self.0[*index])?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for ContainerId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ContainerId({})", self)
+    }
+}
+
+impl FromStr for ContainerId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidContainerId(s.to_string());
+        let uuid = s
+            .strip_prefix('{')
+            .and_then(|s| s.strip_suffix('}'))
+            .unwrap_or(s);
+        let groups = uuid.split('-').collect::<Vec<&str>>();
+        if groups.iter().map(|g| g.len()).collect::<Vec<usize>>() != [8, 4, 4, 4, 12] {
+            return Err(invalid());
+        }
+        let digits = groups.concat();
+        let mut container_id = [0u8; 16];
+        for (n, index) in Self::CANONICAL_ORDER.iter().enumerate() {
+            container_id[*index] = digits
+                .get(n * 2..n * 2 + 2)
+                .and_then(|byte| parse_digits(byte, 16))
+                .ok_or_else(invalid)?;
+        }
+        Ok(Self(container_id))
+    }
+}
+
+/// USB 2.0 Extension capability
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Usb20ExtensionCapability {
+    attributes: u32,
+}
+
+impl Usb20ExtensionCapability {
+    fn from_data(data: &[u8]) -> Result<Self, ParseError> {
+        let data = require("USB 2.0 Extension", data, 7)?;
+        Ok(Self {
+            attributes: u32::from_le_bytes([data[3], data[4], data[5], data[6]]),
+        })
+    }
+
+    /// Raw `bmAttributes` field
+    pub fn attributes(&self) -> u32 {
+        self.attributes
+    }
+
+    /// Link Power Management is supported
+    pub fn lpm(&self) -> bool {
+        self.attributes & 0x0000_0002 != 0
+    }
+
+    /// Best Effort Service Latency (BESL) and alternate HIRD definitions are supported
+    pub fn besl(&self) -> bool {
+        self.attributes & 0x0000_0004 != 0
+    }
+
+    /// Recommended baseline BESL value, if provided
+    pub fn baseline_besl(&self) -> Option<u8> {
+        if self.attributes & 0x0000_0008 != 0 {
+            Some(((self.attributes >> 8) & 0x0f) as u8)
+        } else {
+            None
+        }
+    }
+
+    /// Recommended deep BESL value, if provided
+    pub fn deep_besl(&self) -> Option<u8> {
+        if self.attributes & 0x0000_0010 != 0 {
+            Some(((self.attributes >> 12) & 0x0f) as u8)
+        } else {
+            None
+        }
+    }
+}
+
+/// SuperSpeed USB capability
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SuperSpeedUsbCapability {
+    attributes: u8,
+    speeds_supported: u16,
+    functionality_support: u8,
+    u1_exit_latency: u8,
+    u2_exit_latency: u16,
+}
+
+impl SuperSpeedUsbCapability {
+    fn from_data(data: &[u8]) -> Result<Self, ParseError> {
+        let data = require("SuperSpeed USB", data, 10)?;
+        Ok(Self {
+            attributes: data[3],
+            speeds_supported: u16::from_le_bytes([data[4], data[5]]),
+            functionality_support: data[6],
+            u1_exit_latency: data[7],
+            u2_exit_latency: u16::from_le_bytes([data[8], data[9]]),
+        })
+    }
+
+    /// Latency Tolerance Messages are supported
+    pub fn ltm(&self) -> bool {
+        self.attributes & 0x02 != 0
+    }
+
+    /// Low speed operation is supported
+    pub fn low_speed(&self) -> bool {
+        self.speeds_supported & 0x0001 != 0
+    }
+
+    /// Full speed operation is supported
+    pub fn full_speed(&self) -> bool {
+        self.speeds_supported & 0x0002 != 0
+    }
+
+    /// High speed operation is supported
+    pub fn high_speed(&self) -> bool {
+        self.speeds_supported & 0x0004 != 0
+    }
+
+    /// 5 Gbps operation is supported
+    pub fn super_speed(&self) -> bool {
+        self.speeds_supported & 0x0008 != 0
+    }
+
+    /// Raw `wSpeedsSupported` field
+    pub fn speeds_supported(&self) -> u16 {
+        self.speeds_supported
+    }
+
+    /// Lowest speed at which all functionality is available, as a `wSpeedsSupported` bit index
+    pub fn functionality_support(&self) -> u8 {
+        self.functionality_support
+    }
+
+    /// U1 device exit latency in microseconds
+    pub fn u1_exit_latency(&self) -> u8 {
+        self.u1_exit_latency
+    }
+
+    /// U2 device exit latency in microseconds
+    pub fn u2_exit_latency(&self) -> u16 {
+        self.u2_exit_latency
+    }
+}
+
+/// SuperSpeedPlus sublink speed attribute
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SublinkSpeedAttribute(pub u32);
+
+impl SublinkSpeedAttribute {
+    /// Sublink speed attribute ID
+    pub fn id(&self) -> u8 {
+        (self.0 & 0x0f) as u8
+    }
+
+    /// Sublink is asymmetric
+    pub fn asymmetric(&self) -> bool {
+        self.0 & 0x0040 != 0
+    }
+
+    /// Sublink attribute describes the transmit direction, only meaningful for asymmetric sublinks
+    pub fn transmit(&self) -> bool {
+        self.0 & 0x0080 != 0
+    }
+
+    /// Link protocol, 0 for SuperSpeed and 1 for SuperSpeedPlus
+    pub fn protocol(&self) -> u8 {
+        ((self.0 >> 14) & 0x03) as u8
+    }
+
+    /// Lane speed in bits per second
+    pub fn lane_speed(&self) -> u64 {
+        let mantissa = u64::from(self.0 >> 16);
+        let exponent = (self.0 >> 4) & 0x03;
+        mantissa * 1000u64.pow(exponent)
+    }
+}
+
+/// SuperSpeedPlus capability
+#[derive(Clone, Debug, PartialEq)]
+pub struct SuperSpeedPlusCapability {
+    attributes: u32,
+    functionality_support: u16,
+    sublink_speed_attributes: Vec<SublinkSpeedAttribute>,
+}
+
+impl SuperSpeedPlusCapability {
+    fn from_data(data: &[u8]) -> Result<Self, ParseError> {
+        const DESCRIPTOR: &str = "SuperSpeedPlus";
+
+        let header = require(DESCRIPTOR, data, 12)?;
+        let attributes = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        let functionality_support = u16::from_le_bytes([header[8], header[9]]);
+        let count = ((attributes & 0x1f) + 1) as usize;
+        let sublink_speed_attributes = require(DESCRIPTOR, data, 12 + count * 4)?[12..]
+            .chunks_exact(4)
+            .map(|c| SublinkSpeedAttribute(u32::from_le_bytes([c[0], c[1], c[2], c[3]])))
+            .collect();
+        Ok(Self {
+            attributes,
+            functionality_support,
+            sublink_speed_attributes,
+        })
+    }
+
+    /// Number of sublink speed IDs
+    pub fn sublink_speed_id_count(&self) -> u8 {
+        (((self.attributes >> 5) & 0x0f) + 1) as u8
+    }
+
+    /// Sublink speed attribute ID of the minimum speed at which all functionality is available
+    pub fn min_speed_id(&self) -> u8 {
+        (self.functionality_support & 0x0f) as u8
+    }
+
+    /// Minimum number of receive lanes
+    pub fn min_rx_lanes(&self) -> u8 {
+        ((self.functionality_support >> 8) & 0x0f) as u8
+    }
+
+    /// Minimum number of transmit lanes
+    pub fn min_tx_lanes(&self) -> u8 {
+        ((self.functionality_support >> 12) & 0x0f) as u8
+    }
+
+    /// Sublink speed attributes
+    pub fn sublink_speed_attributes(&self) -> &[SublinkSpeedAttribute] {
+        &self.sublink_speed_attributes
+    }
+}
+
+/// Platform capability
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlatformCapability {
+    uuid: [u8; 16],
+    data: Vec<u8>,
+}
+
+impl PlatformCapability {
+    fn from_data(data: &[u8]) -> Result<Self, ParseError> {
+        require("Platform", data, 20)?;
+        let mut uuid = [0u8; 16];
+        uuid.copy_from_slice(&data[4..20]);
+        Ok(Self {
+            uuid,
+            data: data[20..].to_vec(),
+        })
+    }
+
+    /// Platform capability UUID, as stored in the descriptor
+    pub fn uuid(&self) -> &[u8; 16] {
+        &self.uuid
+    }
+
+    /// Platform specific capability data
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// USB Power Delivery capability
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PowerDeliveryCapability {
+    attributes: u32,
+    bc_version: u16,
+    pd_version: u16,
+    type_c_version: u16,
+}
+
+impl PowerDeliveryCapability {
+    fn from_data(data: &[u8]) -> Result<Self, ParseError> {
+        let data = require("Power Delivery", data, 14)?;
+        Ok(Self {
+            attributes: u32::from_le_bytes([data[4], data[5], data[6], data[7]]),
+            bc_version: u16::from_le_bytes([data[8], data[9]]),
+            pd_version: u16::from_le_bytes([data[10], data[11]]),
+            type_c_version: u16::from_le_bytes([data[12], data[13]]),
+        })
+    }
+
+    /// Raw `bmAttributes` field
+    pub fn attributes(&self) -> u32 {
+        self.attributes
+    }
+
+    /// Battery Charging is supported
+    pub fn battery_charging(&self) -> bool {
+        self.attributes & 0x0000_0002 != 0
+    }
+
+    /// USB Power Delivery is supported
+    pub fn power_delivery(&self) -> bool {
+        self.attributes & 0x0000_0004 != 0
+    }
+
+    /// Device can act as power provider
+    pub fn provider(&self) -> bool {
+        self.attributes & 0x0000_0008 != 0
+    }
+
+    /// Device can act as power consumer
+    pub fn consumer(&self) -> bool {
+        self.attributes & 0x0000_0010 != 0
+    }
+
+    /// Battery Charging specification version, BCD encoded
+    pub fn bc_version(&self) -> u16 {
+        self.bc_version
+    }
+
+    /// Power Delivery specification version, BCD encoded
+    pub fn pd_version(&self) -> u16 {
+        self.pd_version
+    }
+
+    /// USB Type-C specification version, BCD encoded
+    pub fn type_c_version(&self) -> u16 {
+        self.type_c_version
+    }
+}
+
+/// Alternate mode listed in a Billboard capability
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BillboardAlternateMode {
+    /// Standard or Vendor ID
+    pub svid: u16,
+    /// Index of the alternate mode within the SVID
+    pub alternate_mode: u8,
+    /// String descriptor index describing the alternate mode
+    pub string_index: u8,
+    /// Configuration state, 0 unspecified error, 1 not attempted, 2 unsuccessful, 3 successful
+    pub configured: u8,
+}
+
+/// Billboard capability
+#[derive(Clone, Debug, PartialEq)]
+pub struct BillboardCapability {
+    additional_info_url_index: u8,
+    preferred_alternate_mode: u8,
+    vconn_power: u16,
+    version: u16,
+    additional_failure_info: u8,
+    alternate_modes: Vec<BillboardAlternateMode>,
+}
+
+impl BillboardCapability {
+    fn from_data(data: &[u8]) -> Result<Self, ParseError> {
+        const DESCRIPTOR: &str = "Billboard";
+
+        let header = require(DESCRIPTOR, data, 44)?;
+        let count = usize::from(header[4]);
+        let configured = &header[8..40];
+        let alternate_modes = require(DESCRIPTOR, data, 44 + count * 4)?[44..]
+            .chunks_exact(4)
+            .enumerate()
+            .map(|(n, c)| BillboardAlternateMode {
+                svid: u16::from_le_bytes([c[0], c[1]]),
+                alternate_mode: c[2],
+                string_index: c[3],
+                configured: configured
+                    .get(n / 4)
+                    .map_or(0, |c| (c >> ((n % 4) * 2)) & 0x03),
+            })
+            .collect();
+        Ok(Self {
+            additional_info_url_index: header[3],
+            preferred_alternate_mode: header[5],
+            vconn_power: u16::from_le_bytes([header[6], header[7]]),
+            version: u16::from_le_bytes([header[40], header[41]]),
+            additional_failure_info: header[42],
+            alternate_modes,
+        })
+    }
+
+    /// String descriptor index of the additional information URL
+    pub fn additional_info_url_index(&self) -> u8 {
+        self.additional_info_url_index
+    }
+
+    /// Index of the preferred alternate mode
+    pub fn preferred_alternate_mode(&self) -> u8 {
+        self.preferred_alternate_mode
+    }
+
+    /// Raw `VconnPower` field
+    pub fn vconn_power(&self) -> u16 {
+        self.vconn_power
+    }
+
+    /// Billboard specification version, BCD encoded
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// Raw `bAdditionalFailureInfo` field
+    pub fn additional_failure_info(&self) -> u8 {
+        self.additional_failure_info
+    }
+
+    /// Alternate modes supported by the device
+    pub fn alternate_modes(&self) -> &[BillboardAlternateMode] {
+        &self.alternate_modes
+    }
+}
+
+/// Device capability from a BOS descriptor
+#[derive(Clone, Debug, PartialEq)]
+pub enum DeviceCapability {
+    /// USB 2.0 Extension
+    Usb20Extension(Usb20ExtensionCapability),
+    /// SuperSpeed USB
+    SuperSpeedUsb(SuperSpeedUsbCapability),
+    /// SuperSpeedPlus
+    SuperSpeedPlus(SuperSpeedPlusCapability),
+    /// Container ID
+    ContainerId(ContainerId),
+    /// Platform
+    Platform(PlatformCapability),
+    /// USB Power Delivery
+    PowerDelivery(PowerDeliveryCapability),
+    /// Billboard
+    Billboard(BillboardCapability),
+    /// Precision Time Measurement, the capability has no fields
+    PrecisionTimeMeasurement,
+    /// Capability without a parser, with the complete descriptor data
+    Unknown {
+        /// Capability type
+        capability_type: u8,
+        /// Descriptor data, including the header
+        data: Vec<u8>,
+    },
+}
+
+impl DeviceCapability {
+    const DESCRIPTOR_TYPE_DEVICE_CAPABILITY: u8 = 0x10;
+
+    /// Parse a single device capability descriptor
+    pub fn from_data(data: &[u8]) -> Result<Self, ParseError> {
+        let data = &data[..Self::descriptor_length(data)?];
+        Self::from_descriptor(data)
+    }
+
+    /// Check the capability header, returns the length of the descriptor
+    fn descriptor_length(data: &[u8]) -> Result<usize, ParseError> {
+        const DESCRIPTOR: &str = "Device capability";
+
+        let header = require(DESCRIPTOR, data, 3)?;
+        if usize::from(header[0]) < 3 || usize::from(header[0]) > data.len() {
+            return Err(ParseError::InvalidLength {
+                descriptor: DESCRIPTOR,
+                length: usize::from(header[0]),
+            });
+        }
+        if header[1] != Self::DESCRIPTOR_TYPE_DEVICE_CAPABILITY {
+            return Err(ParseError::InvalidDescriptorType {
+                descriptor: DESCRIPTOR,
+                expected: Self::DESCRIPTOR_TYPE_DEVICE_CAPABILITY,
+                actual: header[1],
+            });
+        }
+        Ok(usize::from(header[0]))
+    }
+
+    /// Parse a capability with a checked header, `data` is exactly the descriptor
+    fn from_descriptor(data: &[u8]) -> Result<Self, ParseError> {
+        let capability_type = data[2];
+        let capability = match DeviceCapabilityType::from(capability_type) {
+            DeviceCapabilityType::Usb20Extension => {
+                Self::Usb20Extension(Usb20ExtensionCapability::from_data(data)?)
+            }
+            DeviceCapabilityType::SuperSpeedUsb => {
+                Self::SuperSpeedUsb(SuperSpeedUsbCapability::from_data(data)?)
+            }
+            DeviceCapabilityType::SuperSpeedPlus => {
+                Self::SuperSpeedPlus(SuperSpeedPlusCapability::from_data(data)?)
+            }
+            DeviceCapabilityType::ContainerId => {
+                let data = require("Container ID", data, 20)?;
+                let mut container_id = [0u8; 16];
+                container_id.copy_from_slice(&data[4..20]);
+                Self::ContainerId(ContainerId(container_id))
+            }
+            DeviceCapabilityType::Platform => Self::Platform(PlatformCapability::from_data(data)?),
+            DeviceCapabilityType::PowerDeliveryCapability => {
+                Self::PowerDelivery(PowerDeliveryCapability::from_data(data)?)
+            }
+            DeviceCapabilityType::Billboard => {
+                Self::Billboard(BillboardCapability::from_data(data)?)
+            }
+            DeviceCapabilityType::PrecisionTimeMeasurement => Self::PrecisionTimeMeasurement,
+            _ => Self::Unknown {
+                capability_type,
+                data: data.to_vec(),
+            },
+        };
+        Ok(capability)
+    }
+
+    /// Capability type
+    pub fn capability_type(&self) -> DeviceCapabilityType {
+        match self {
+            Self::Usb20Extension(_) => DeviceCapabilityType::Usb20Extension,
+            Self::SuperSpeedUsb(_) => DeviceCapabilityType::SuperSpeedUsb,
+            Self::SuperSpeedPlus(_) => DeviceCapabilityType::SuperSpeedPlus,
+            Self::ContainerId(_) => DeviceCapabilityType::ContainerId,
+            Self::Platform(_) => DeviceCapabilityType::Platform,
+            Self::PowerDelivery(_) => DeviceCapabilityType::PowerDeliveryCapability,
+            Self::Billboard(_) => DeviceCapabilityType::Billboard,
+            Self::PrecisionTimeMeasurement => DeviceCapabilityType::PrecisionTimeMeasurement,
+            Self::Unknown {
+                capability_type, ..
+            } => DeviceCapabilityType::from(*capability_type),
+        }
+    }
+}
+
+/// Binary device Object Store (BOS) descriptor
+#[derive(Clone, Debug, PartialEq)]
+pub struct BinaryObjectStoreDescriptor {
+    data: Vec<u8>,
+    capabilities: Vec<DeviceCapability>,
+}
+
+impl BinaryObjectStoreDescriptor {
+    const DESCRIPTOR_TYPE_BOS: u8 = 0x0f;
+
+    /// Parse a BOS descriptor including all device capabilities
+    ///
+    /// Data beyond the total length declared by the descriptor is ignored. Only an invalid BOS
+    /// header or total length is an error. Capabilities which cannot be parsed are kept as
+    /// unknown, and when the capabilities end early or a capability header is invalid, the
+    /// capabilities parsed so far are kept.
+    pub fn from_data(data: &[u8]) -> Result<Self, ParseError> {
+        const DESCRIPTOR: &str = "BOS";
+        const LENGTH: usize = 5;
+
+        let header = require(DESCRIPTOR, data, LENGTH)?;
+        if usize::from(header[0]) != LENGTH {
+            return Err(ParseError::InvalidLength {
+                descriptor: DESCRIPTOR,
+                length: usize::from(header[0]),
+            });
+        }
+        if header[1] != Self::DESCRIPTOR_TYPE_BOS {
+            return Err(ParseError::InvalidDescriptorType {
+                descriptor: DESCRIPTOR,
+                expected: Self::DESCRIPTOR_TYPE_BOS,
+                actual: header[1],
+            });
+        }
+        let total = usize::from(u16::from_le_bytes([header[2], header[3]]));
+        if total < LENGTH {
+            return Err(ParseError::InvalidLength {
+                descriptor: DESCRIPTOR,
+                length: total,
+            });
+        }
+        let data = require(DESCRIPTOR, data, total)?;
+        let declared = header[4];
+
+        let mut capabilities = Vec::with_capacity(usize::from(declared));
+        let mut part = &data[LENGTH..];
+        for found in 0..declared {
+            if part.is_empty() {
+                trace!("{}", ParseError::MissingCapabilities { declared, found });
+                break;
+            }
+            // Without a valid header the following capabilities cannot be located
+            let length = match DeviceCapability::descriptor_length(part) {
+                Ok(length) => length,
+                Err(e) => {
+                    trace!(
+                        "{}, {}",
+                        e,
+                        ParseError::MissingCapabilities { declared, found }
+                    );
+                    break;
+                }
+            };
+            // A malformed capability is kept as unknown, the others are still usable
+            let capability =
+                DeviceCapability::from_descriptor(&part[..length]).unwrap_or_else(|e| {
+                    trace!("{}", e);
+                    DeviceCapability::Unknown {
+                        capability_type: part[2],
+                        data: part[..length].to_vec(),
+                    }
+                });
+            capabilities.push(capability);
+            part = &part[length..];
+        }
+
+        Ok(Self {
+            data: data.to_vec(),
+            capabilities,
+        })
+    }
+
+    /// Raw descriptor data
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Iterate over the device capabilities
+    pub fn capabilities(&self) -> std::slice::Iter<'_, DeviceCapability> {
+        self.capabilities.iter()
+    }
+
+    /// Get container id
+    pub fn container_id(&self) -> Option<ContainerId> {
+        self.capabilities().find_map(|capability| match capability {
+            DeviceCapability::ContainerId(container_id) => Some(container_id.clone()),
+            _ => None,
+        })
+    }
+}
+
+/// Get the first `required` bytes of a descriptor
+fn require<'a>(
+    descriptor: &'static str,
+    data: &'a [u8],
+    required: usize,
+) -> Result<&'a [u8], ParseError> {
+    data.get(..required).ok_or(ParseError::TooShort {
+        descriptor,
+        required,
+        actual: data.len(),
+    })
+}
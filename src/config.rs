@@ -0,0 +1,141 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::alias::AliasEntry;
+use crate::{Aliases, Error, Location, PortLocation, Quirk, Quirks};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    timeout: Option<u64>,
+    settle: Option<u64>,
+    hub: Option<String>,
+    format: Option<String>,
+    #[serde(default)]
+    protected: Vec<String>,
+    #[serde(default)]
+    aliases: BTreeMap<String, AliasEntry>,
+    #[serde(default)]
+    quirk: Vec<Quirk>,
+}
+
+/// uhubctrl configuration
+///
+/// The configuration is stored in TOML, for example
+///
+/// ```toml
+/// # Control request timeout, in milliseconds
+/// timeout = 1000
+/// # Time to wait for devices to disconnect and reconnect, in milliseconds
+/// settle = 3000
+/// # Hub used when no hub is given
+/// hub = "1-2"
+/// format = "text"
+/// # Ports which are not switched off without --force
+/// protected = ["1-2:1", "dut-rpi4"]
+///
+/// [aliases]
+/// dut-rpi4 = "1-2.3:4"
+///
+/// [[quirk]]
+/// vendor_id = 0x05e3
+/// product_id = 0x0610
+/// ganged = true
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    /// Timeout of control requests
+    pub timeout: Option<Duration>,
+    /// Time to wait for devices to disconnect and reconnect
+    pub settle: Option<Duration>,
+    /// Hub used when no hub is given
+    pub hub: Option<Location>,
+    /// Output format
+    pub format: Option<String>,
+    /// Protected ports, as port aliases or `<hub location>:<port>`
+    pub protected: Vec<String>,
+    /// Port aliases
+    pub aliases: Aliases,
+    /// Hub quirks from `quirk` tables as in the quirks file, extending the built-in quirks
+    pub quirks: Quirks,
+}
+
+impl Config {
+    /// Parse configuration from TOML
+    pub fn from_toml(toml: &str) -> Result<Self, Error> {
+        let invalid = |message: String| Error::InvalidConfig {
+            path: None,
+            message,
+        };
+        let file = toml::from_str::<ConfigFile>(toml).map_err(|e| invalid(e.to_string()))?;
+        let hub = file
+            .hub
+            .map(|hub| hub.parse::<Location>())
+            .transpose()
+            .map_err(|e| invalid(e.to_string()))?;
+        let aliases = Aliases::from_entries(file.aliases).map_err(invalid)?;
+        let mut quirks = Quirks::default();
+        for quirk in file.quirk {
+            quirks.insert(quirk);
+        }
+        Ok(Self {
+            timeout: file.timeout.map(Duration::from_millis),
+            settle: file.settle.map(Duration::from_millis),
+            hub,
+            format: file.format,
+            protected: file.protected,
+            aliases,
+            quirks,
+        })
+    }
+
+    /// Load configuration from a TOML file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let toml = std::fs::read_to_string(path).map_err(|source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_toml(&toml).map_err(|e| match e {
+            Error::InvalidConfig { message, .. } => Error::InvalidConfig {
+                path: Some(path.to_path_buf()),
+                message,
+            },
+            e => e,
+        })
+    }
+
+    /// Merge a configuration loaded later, its settings take precedence
+    pub fn merge(&mut self, config: Config) {
+        self.timeout = config.timeout.or(self.timeout);
+        self.settle = config.settle.or(self.settle);
+        self.hub = config.hub.or(self.hub.take());
+        self.format = config.format.or(self.format.take());
+        self.protected.extend(config.protected);
+        self.aliases.extend(config.aliases);
+        self.quirks.extend(config.quirks);
+    }
+
+    /// Resolve the protected ports, including the ports of companion hubs
+    pub fn protected_ports(&self) -> Result<Vec<PortLocation>, Error> {
+        let mut ports = Vec::new();
+        for name in self.protected.iter() {
+            let alias = self.aliases.resolve(name)?;
+            for hub in alias.hubs() {
+                ports.push(PortLocation::new(hub.clone(), alias.port.port));
+            }
+        }
+        Ok(ports)
+    }
+
+    /// Check if a port is protected, unresolvable entries are ignored
+    pub fn is_protected(&self, port: &PortLocation) -> bool {
+        self.protected
+            .iter()
+            .filter_map(|name| self.aliases.resolve(name).ok())
+            .any(|alias| alias.port.port == port.port && alias.hubs().any(|h| h == &port.hub))
+    }
+}
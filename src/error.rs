@@ -1,41 +1,365 @@
+use std::error::Error as StdError;
+use std::path::PathBuf;
+
+use nusb::transfer::TransferError;
+
+use crate::{Location, LogicalPowerSwitchingMode};
+
+/// Control request which failed, with the hub and port it was addressed to
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestContext {
+    /// Name of the request, such as `GET_STATUS`
+    pub request: &'static str,
+    /// Location of the hub
+    pub location: Location,
+    /// Port the request was addressed to, if any
+    pub port: Option<u8>,
+}
+
+impl std::fmt::Display for RequestContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} to {}", self.request, self.location)?;
+        if let Some(port) = self.port {
+            write!(f, " port {}", port)?;
+        }
+        Ok(())
+    }
+}
+
 /// USB hub control error
 #[derive(Debug)]
 pub enum Error {
-    /// USB error reported by `nusb`
-    UsbError(nusb::Error),
-    /// USB transfer error reported by `nusb`
-    UsbTransferError(nusb::transfer::TransferError),
-    /// I/O error reported by `std::io`
-    IoError(std::io::Error),
-    /// Device with invalid device class was provided
-    InvalidDeviceClass,
-    /// Transfer responded with invalid data
-    InvalidRespone,
-    /// Invalid port provided
-    InvalidPort,
-}
-
-impl From<nusb::Error> for Error {
-    fn from(error: nusb::Error) -> Self {
-        Error::UsbError(error)
-    }
+    /// Enumerating USB devices failed
+    Enumerate(std::io::Error),
+    /// Opening the device failed
+    Open {
+        /// Location of the device
+        location: Location,
+        /// Error reported by `nusb`
+        source: std::io::Error,
+    },
+    /// The user is not allowed to open the device
+    PermissionDenied {
+        /// Location of the device
+        location: Location,
+        /// Error reported by `nusb`
+        source: std::io::Error,
+    },
+    /// The device stalled the request, usually because the request is not supported
+    Stall(RequestContext),
+    /// The request timed out
+    Timeout(RequestContext),
+    /// The device was disconnected
+    Disconnected(RequestContext),
+    /// The request failed for another reason
+    Transfer {
+        /// Failed request
+        context: RequestContext,
+        /// Error reported by `nusb`
+        source: TransferError,
+    },
+    /// The device responded with fewer bytes than expected
+    InvalidResponse {
+        /// Failed request
+        context: RequestContext,
+        /// Expected number of bytes
+        expected: usize,
+        /// Received number of bytes
+        actual: usize,
+    },
+    /// Descriptor provided by the device could not be parsed
+    InvalidDescriptor {
+        /// Failed request
+        context: RequestContext,
+        /// Parse error
+        source: ParseError,
+    },
+    /// Device is not a hub
+    InvalidDeviceClass {
+        /// Location of the device
+        location: Location,
+        /// Device class
+        class: u8,
+    },
+    /// No device is attached at the location
+    NoDevice {
+        /// Location of the device
+        location: Location,
+    },
+    /// Port is not present on the hub
+    InvalidPort {
+        /// Location of the hub
+        location: Location,
+        /// Requested port
+        port: u8,
+        /// Number of hub ports
+        port_count: u8,
+    },
+    /// Hub does not support switching the power of individual ports
+    UnsupportedSwitchingMode {
+        /// Location of the hub
+        location: Location,
+        /// Switching mode reported by the hub
+        mode: LogicalPowerSwitchingMode,
+    },
+    /// Switching the port off is known not to cut VBUS, from the hub quirk
+    NoVbusSwitching {
+        /// Location of the hub
+        location: Location,
+        /// Requested port
+        port: u8,
+    },
+    /// The hub quirk requires switching the companion hub, which is not set
+    CompanionRequired {
+        /// Location of the hub
+        location: Location,
+    },
+    /// Accessing a sysfs attribute failed
+    Io {
+        /// Path of the attribute
+        path: PathBuf,
+        /// Error reported by `std::io`
+        source: std::io::Error,
+    },
+    /// Quirks file could not be parsed
+    InvalidQuirks {
+        /// Path of the quirks file, if loaded from a file
+        path: Option<PathBuf>,
+        /// Parse error
+        message: String,
+    },
+    /// Aliases file could not be parsed
+    InvalidAliases {
+        /// Path of the aliases file, if loaded from a file
+        path: Option<PathBuf>,
+        /// Parse error
+        message: String,
+    },
+    /// Configuration file could not be parsed
+    InvalidConfig {
+        /// Path of the configuration file, if loaded from a file
+        path: Option<PathBuf>,
+        /// Parse error
+        message: String,
+    },
+    /// No alias with the name exists
+    UnknownAlias(String),
+    /// Invalid location provided
+    InvalidLocation(String),
+    /// Invalid container id provided
+    InvalidContainerId(String),
+    /// Invalid port list provided
+    InvalidPortList(String),
 }
 
-impl From<nusb::transfer::TransferError> for Error {
-    fn from(error: nusb::transfer::TransferError) -> Self {
-        Error::UsbTransferError(error)
+impl Error {
+    /// Create an error for a failed transfer
+    pub(crate) fn transfer(context: RequestContext, error: TransferError) -> Self {
+        match error {
+            TransferError::Stall => Self::Stall(context),
+            // Blocking transfers are cancelled when the timeout expires
+            TransferError::Cancelled => Self::Timeout(context),
+            TransferError::Disconnected => Self::Disconnected(context),
+            source => Self::Transfer { context, source },
+        }
+    }
+
+    /// Create an error for a device which could not be opened
+    pub(crate) fn open(location: Location, error: nusb::Error) -> Self {
+        let source = std::io::Error::from(error);
+        if source.kind() == std::io::ErrorKind::PermissionDenied {
+            Self::PermissionDenied { location, source }
+        } else {
+            Self::Open { location, source }
+        }
     }
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::UsbError(e) => write!(f, "{}", e),
-            Self::UsbTransferError(e) => write!(f, "{}", e),
-            Self::IoError(e) => write!(f, "{}", e),
-            Self::InvalidDeviceClass => write!(f, "Invalid class"),
-            Self::InvalidRespone => write!(f, "Invalid response"),
-            Self::InvalidPort => write!(f, "Invalid port"),
+            Self::Enumerate(e) => write!(f, "Failed to enumerate devices, {}", e),
+            Self::Open { location, source } => write!(f, "Failed to open {}, {}", location, source),
+            Self::PermissionDenied { location, .. } => write!(
+                f,
+                "Permission denied opening {}, a udev rule granting access to the device is required",
+                location
+            ),
+            Self::Stall(context) => write!(f, "{} stalled, request not supported", context),
+            Self::Timeout(context) => write!(f, "{} timed out", context),
+            Self::Disconnected(context) => write!(f, "{} failed, device disconnected", context),
+            Self::Transfer { context, source } => write!(f, "{} failed, {}", context, source),
+            Self::InvalidResponse {
+                context,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} returned {} bytes, expected {}",
+                context, actual, expected
+            ),
+            Self::InvalidDescriptor { context, source } => {
+                write!(f, "{} returned invalid descriptor, {}", context, source)
+            }
+            Self::InvalidDeviceClass { location, class } => {
+                write!(
+                    f,
+                    "Device {} with class {:02x} is not a hub",
+                    location, class
+                )
+            }
+            Self::NoDevice { location } => write!(f, "No device attached at {}", location),
+            Self::InvalidPort {
+                location,
+                port,
+                port_count,
+            } => write!(
+                f,
+                "Invalid port {} for hub {} with {} ports",
+                port, location, port_count
+            ),
+            Self::UnsupportedSwitchingMode { location, mode } => write!(
+                f,
+                "Hub {} does not support individual port power switching ({:?})",
+                location, mode
+            ),
+            Self::NoVbusSwitching { location, port } => write!(
+                f,
+                "Hub {} port {} does not cut VBUS when switched off",
+                location, port
+            ),
+            Self::CompanionRequired { location } => write!(
+                f,
+                "Hub {} requires its companion hub to be switched together",
+                location
+            ),
+            Self::Io { path, source } => {
+                write!(f, "Failed to access {}, {}", path.display(), source)
+            }
+            Self::InvalidQuirks {
+                path: Some(path),
+                message,
+            } => write!(f, "Invalid quirks file {}, {}", path.display(), message),
+            Self::InvalidQuirks {
+                path: None,
+                message,
+            } => write!(f, "Invalid quirks, {}", message),
+            Self::InvalidAliases {
+                path: Some(path),
+                message,
+            } => write!(f, "Invalid aliases file {}, {}", path.display(), message),
+            Self::InvalidAliases {
+                path: None,
+                message,
+            } => write!(f, "Invalid aliases, {}", message),
+            Self::InvalidConfig {
+                path: Some(path),
+                message,
+            } => write!(
+                f,
+                "Invalid configuration file {}, {}",
+                path.display(),
+                message
+            ),
+            Self::InvalidConfig {
+                path: None,
+                message,
+            } => write!(f, "Invalid configuration, {}", message),
+            Self::UnknownAlias(name) => write!(f, "Unknown port alias {}", name),
+            Self::InvalidLocation(location) => write!(f, "Invalid location {}", location),
+            Self::InvalidContainerId(container_id) => {
+                write!(f, "Invalid container id {}", container_id)
+            }
+            Self::InvalidPortList(ports) => write!(
+                f,
+                "Invalid port list {}, expected ports such as 1,3-5 or all",
+                ports
+            ),
         }
     }
 }
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::Enumerate(e) => Some(e),
+            Self::Open { source, .. } | Self::PermissionDenied { source, .. } => Some(source),
+            Self::Transfer { source, .. } => Some(source),
+            Self::Io { source, .. } => Some(source),
+            Self::InvalidDescriptor { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Descriptor parse error
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// Fewer bytes were provided than the descriptor requires
+    TooShort {
+        /// Descriptor name
+        descriptor: &'static str,
+        /// Required number of bytes
+        required: usize,
+        /// Provided number of bytes
+        actual: usize,
+    },
+    /// The descriptor length field holds an invalid value
+    InvalidLength {
+        /// Descriptor name
+        descriptor: &'static str,
+        /// Value of the length field
+        length: usize,
+    },
+    /// The descriptor type field does not match the descriptor
+    InvalidDescriptorType {
+        /// Descriptor name
+        descriptor: &'static str,
+        /// Expected descriptor type
+        expected: u8,
+        /// Descriptor type found
+        actual: u8,
+    },
+    /// The descriptor declares more device capabilities than it contains
+    MissingCapabilities {
+        /// Number of declared capabilities
+        declared: u8,
+        /// Number of capabilities found
+        found: u8,
+    },
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooShort {
+                descriptor,
+                required,
+                actual,
+            } => write!(
+                f,
+                "{} descriptor too short, {} bytes required, {} provided",
+                descriptor, required, actual
+            ),
+            Self::InvalidLength { descriptor, length } => {
+                write!(f, "{} descriptor has invalid length {}", descriptor, length)
+            }
+            Self::InvalidDescriptorType {
+                descriptor,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} descriptor has type {:02x}, expected {:02x}",
+                descriptor, actual, expected
+            ),
+            Self::MissingCapabilities { declared, found } => write!(
+                f,
+                "BOS descriptor declares {} capabilities, {} found",
+                declared, found
+            ),
+        }
+    }
+}
+
+impl StdError for ParseError {}
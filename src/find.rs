@@ -0,0 +1,85 @@
+use serde::Serialize;
+
+use crate::{DeviceSummary, Location, PortLocation, Topology};
+
+/// Criteria selecting devices, a device matches when it matches all given criteria
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DeviceSelector {
+    /// Vendor ID
+    pub vendor_id: Option<u16>,
+    /// Product ID
+    pub product_id: Option<u16>,
+    /// Serial number string
+    pub serial_number: Option<String>,
+    /// Bus number and device address
+    pub bus_address: Option<(u8, u8)>,
+    /// Product string
+    pub product: Option<String>,
+}
+
+impl DeviceSelector {
+    /// Check if no criteria is given, such a selector matches all devices
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Check if a device matches all criteria
+    pub fn matches(&self, device: &DeviceSummary) -> bool {
+        self.vendor_id.is_none_or(|v| v == device.vendor_id)
+            && self.product_id.is_none_or(|p| p == device.product_id)
+            && self
+                .serial_number
+                .as_deref()
+                .is_none_or(|s| device.serial_number.as_deref() == Some(s))
+            && self.bus_address.is_none_or(|(busnum, address)| {
+                busnum == device.busnum && Some(address) == device.address
+            })
+            && self
+                .product
+                .as_deref()
+                .is_none_or(|p| device.product.as_deref() == Some(p))
+    }
+}
+
+/// Device found in the topology, with the hub port it is attached to
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FoundDevice {
+    /// Device
+    #[serde(flatten)]
+    pub device: DeviceSummary,
+    /// Hub port the device is attached to, `None` for root hubs
+    pub port: Option<PortLocation>,
+    /// Hub ports leading from the root hub to the device
+    pub path: Vec<PortLocation>,
+}
+
+impl FoundDevice {
+    /// Locate a device from its location
+    pub fn new(device: DeviceSummary) -> Self {
+        let path = Self::port_path(&device.location);
+        Self {
+            port: path.last().cloned(),
+            path,
+            device,
+        }
+    }
+
+    /// Hub ports leading from the root hub to a location
+    pub fn port_path(location: &Location) -> Vec<PortLocation> {
+        let chain = location.port_chain();
+        (0..chain.len())
+            .map(|n| PortLocation::new(Location::new(location.busnum(), &chain[..n]), chain[n]))
+            .collect()
+    }
+}
+
+impl Topology {
+    /// Find the devices matching the selector, ordered by location
+    pub fn find(&self, selector: &DeviceSelector) -> Vec<FoundDevice> {
+        self.devices()
+            .map(|(_, info)| DeviceSummary::from_device_info(info))
+            .filter(|device| selector.matches(device))
+            .map(FoundDevice::new)
+            .collect()
+    }
+}
@@ -5,38 +5,125 @@
 use std::hash::Hash;
 use std::time::Duration;
 
-use log::trace;
+use log::{info, trace};
 use nusb::MaybeFuture;
 use nusb::transfer::{Control, ControlType, Recipient};
 use nusb::{Device, DeviceInfo};
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
 
+mod alias;
+mod bos;
+mod config;
 mod error;
+mod find;
+mod monitor;
+mod quirks;
+mod state;
+mod sysfs;
+mod topology;
+mod udev;
 
-pub use error::Error;
+pub use alias::{Aliases, PortAlias, PortList, PortLocation};
+pub use bos::{
+    BillboardAlternateMode, BillboardCapability, BinaryObjectStoreDescriptor, ContainerId,
+    DeviceCapability, DeviceCapabilityType, PlatformCapability, PowerDeliveryCapability,
+    SublinkSpeedAttribute, SuperSpeedPlusCapability, SuperSpeedUsbCapability,
+    Usb20ExtensionCapability,
+};
+pub use config::Config;
+pub use error::{Error, ParseError, RequestContext};
+pub use find::{DeviceSelector, FoundDevice};
+pub use monitor::{Event, HubSnapshot, Snapshot};
+pub use quirks::{Quirk, Quirks};
+pub use state::{DeviceSummary, HubState, PortState};
+pub use sysfs::{SYSFS_ROOT, Sysfs, SysfsDevice, SysfsHub, SysfsPort};
+pub use topology::{DEVICE_CLASS_HUB, Location, Topology, parse_digits};
+pub use udev::UdevRule;
 
 /// USB version 3.0 code
 pub const USB_VERSION_3_0: u16 = 0x0300;
 
+/// Default timeout of control requests
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Method used to switch hub ports
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PortControlBackend {
+    /// Hub class port power requests over USB
+    #[default]
+    Usb,
+    /// Linux sysfs port `disable` attribute
+    Sysfs,
+    /// Hub class requests, falling back to sysfs when the hub does not support them
+    Auto,
+}
+
+impl PortControlBackend {
+    /// Backend used for a port of a hub with the given power switching mode and quirk
+    ///
+    /// [`PortControlBackend::Auto`] resolves to sysfs when the hub does not support individual
+    /// port power switching or the quirk marks the port as not cutting VBUS, for switching on as
+    /// well as off, so that a port switched off through sysfs is also switched on through sysfs.
+    pub fn resolve(self, mode: LogicalPowerSwitchingMode, quirk: Option<&Quirk>, port: u8) -> Self {
+        match self {
+            Self::Auto
+                if mode != LogicalPowerSwitchingMode::IndividualPort
+                    || quirk.is_some_and(|q| q.no_vbus(port)) =>
+            {
+                Self::Sysfs
+            }
+            Self::Auto => Self::Usb,
+            backend => backend,
+        }
+    }
+}
+
 /// USB hub
 pub struct Hub {
     info: DeviceInfo,
     device: Device,
     hub_descriptor: HubDescriptor,
     super_speed: bool,
-    container_id: Option<ContainerId>,
+    bos_descriptor: Option<BinaryObjectStoreDescriptor>,
+    sysfs: Sysfs,
+    quirk: Option<Quirk>,
+    timeout: Duration,
+    dry_run: bool,
+    companion: Option<Box<Hub>>,
 }
 
 impl Hub {
-    /// Create a Hub from DeviceInfo
+    /// Create a Hub from DeviceInfo, applying the built-in quirks
     pub fn from_device_info(info: &DeviceInfo) -> Result<Self, Error> {
-        const DEVICE_CLASS_HUB: u8 = 0x09;
+        Self::from_device_info_with_quirks(info, &Quirks::builtin())
+    }
+
+    /// Create a Hub from DeviceInfo, applying the matching quirk from `quirks`
+    pub fn from_device_info_with_quirks(info: &DeviceInfo, quirks: &Quirks) -> Result<Self, Error> {
+        Self::open(info, quirks, DEFAULT_TIMEOUT)
+    }
+
+    /// Create a Hub from DeviceInfo, applying the matching quirk from `quirks`
+    ///
+    /// The timeout applies to the descriptor requests made while opening the hub and to the
+    /// requests made later, see [`Hub::set_timeout`].
+    pub fn open(info: &DeviceInfo, quirks: &Quirks, timeout: Duration) -> Result<Self, Error> {
+        let location = Location::from_device_info(info);
         if info.class() != DEVICE_CLASS_HUB {
-            Err(Error::InvalidDeviceClass)
+            Err(Error::InvalidDeviceClass {
+                location,
+                class: info.class(),
+            })
         } else {
-            let device = info.open().wait()?;
+            let device = info
+                .open()
+                .wait()
+                .map_err(|e| Error::open(location.clone(), e))?;
             let descriptor = device.device_descriptor();
             let super_speed = descriptor.usb_version() > USB_VERSION_3_0;
-            let hub_descriptor = Self::get_hub_description(&device, super_speed)?;
+            let hub_descriptor =
+                Self::get_hub_description(&device, &location, super_speed, timeout)?;
 
             let lpsm = hub_descriptor.logical_power_switching_mode();
             let lpsm_str = match lpsm {
@@ -45,9 +132,18 @@ impl Hub {
                 _ => "unknown",
             };
 
-            let container_id = match Self::get_bos_description(&device) {
-                Ok(bos) => bos.container_id(),
-                Err(_) => None,
+            let quirk = quirks.lookup(info.vendor_id(), info.product_id(), info.device_version());
+
+            let bos_descriptor = if quirk.as_ref().is_some_and(|q| q.broken_bos) {
+                None
+            } else {
+                match Self::get_bos_description(&device, &location, timeout) {
+                    Ok(bos) => Some(bos),
+                    Err(e) => {
+                        trace!("{}", e);
+                        None
+                    }
+                }
             };
 
             trace!(
@@ -63,75 +159,89 @@ impl Hub {
                 device,
                 hub_descriptor,
                 super_speed,
-                container_id,
+                bos_descriptor,
+                sysfs: Sysfs::default(),
+                quirk,
+                timeout,
+                dry_run: false,
+                companion: None,
             })
         }
     }
 
-    fn get_hub_description(device: &Device, super_speed: bool) -> Result<HubDescriptor, Error> {
+    fn get_hub_description(
+        device: &Device,
+        location: &Location,
+        super_speed: bool,
+        timeout: Duration,
+    ) -> Result<HubDescriptor, Error> {
         const STANDARD_REQUEST_GET_DESCRIPTOR: u8 = 0x06;
 
-        const DESCRIPTOR_TYPE_HUB: u8 = 0x29;
-        const DESCRIPTOR_TYPE_SUPERSPEED_HUB: u8 = 0x2a;
-
+        let context = RequestContext {
+            request: "GET_DESCRIPTOR(HUB)",
+            location: location.clone(),
+            port: None,
+        };
         let (descriptor_type, request_size) = if super_speed {
-            (DESCRIPTOR_TYPE_SUPERSPEED_HUB, 12)
+            (HubDescriptor::DESCRIPTOR_TYPE_SUPERSPEED_HUB, 12)
         } else {
-            (DESCRIPTOR_TYPE_HUB, 9)
+            (HubDescriptor::DESCRIPTOR_TYPE_HUB, 11)
         };
         let mut buf = vec![0; request_size];
-        let len = device.control_in_blocking(
-            Control {
-                control_type: ControlType::Class,
-                recipient: Recipient::Device,
-                request: STANDARD_REQUEST_GET_DESCRIPTOR,
-                value: ((descriptor_type as u16) << 8),
-                index: 0,
-            },
-            &mut buf,
-            Duration::from_secs(5),
-        )?;
-
-        if len != request_size {
-            return Err(Error::InvalidRespone);
-        }
-        buf.truncate(len);
+        let len = device
+            .control_in_blocking(
+                Control {
+                    control_type: ControlType::Class,
+                    recipient: Recipient::Device,
+                    request: STANDARD_REQUEST_GET_DESCRIPTOR,
+                    value: ((descriptor_type as u16) << 8),
+                    index: 0,
+                },
+                &mut buf,
+                timeout,
+            )
+            .map_err(|e| Error::transfer(context.clone(), e))?;
 
-        let port_count = if buf[2] <= 15 { buf[2] } else { 0 };
-        let characteristics = u16::from_le_bytes(buf[3..=4].try_into().unwrap());
+        buf.truncate(len);
 
-        Ok(HubDescriptor {
-            port_count,
-            characteristics,
-        })
+        HubDescriptor::from_data(&buf)
+            .map_err(|source| Error::InvalidDescriptor { context, source })
     }
 
-    fn get_bos_description(device: &Device) -> Result<BinaryObjectStoreDescriptor, Error> {
+    fn get_bos_description(
+        device: &Device,
+        location: &Location,
+        timeout: Duration,
+    ) -> Result<BinaryObjectStoreDescriptor, Error> {
         const STANDARD_REQUEST_GET_DESCRIPTOR: u8 = 0x06;
 
         // Binary device Object Store (BOS)
         const DESCRIPTOR_TYPE_BOS: u8 = 0x0f;
 
+        let context = RequestContext {
+            request: "GET_DESCRIPTOR(BOS)",
+            location: location.clone(),
+            port: None,
+        };
         let mut buf = vec![0; 4096];
-        let len = device.control_in_blocking(
-            Control {
-                control_type: ControlType::Standard,
-                recipient: Recipient::Device,
-                request: STANDARD_REQUEST_GET_DESCRIPTOR,
-                value: ((DESCRIPTOR_TYPE_BOS as u16) << 8),
-                index: 0,
-            },
-            &mut buf,
-            Duration::from_secs(5),
-        )?;
+        let len = device
+            .control_in_blocking(
+                Control {
+                    control_type: ControlType::Standard,
+                    recipient: Recipient::Device,
+                    request: STANDARD_REQUEST_GET_DESCRIPTOR,
+                    value: ((DESCRIPTOR_TYPE_BOS as u16) << 8),
+                    index: 0,
+                },
+                &mut buf,
+                timeout,
+            )
+            .map_err(|e| Error::transfer(context.clone(), e))?;
 
         buf.truncate(len);
 
-        if len >= 5 {
-            Ok(BinaryObjectStoreDescriptor::from_data(&buf))
-        } else {
-            Err(Error::InvalidRespone)
-        }
+        BinaryObjectStoreDescriptor::from_data(&buf)
+            .map_err(|source| Error::InvalidDescriptor { context, source })
     }
 
     /// Get DeviceInfo for Hub
@@ -139,83 +249,372 @@ impl Hub {
         self.info.clone()
     }
 
+    /// Get Hub location
+    pub fn location(&self) -> Location {
+        Location::from_device_info(&self.info)
+    }
+
+    /// Set the sysfs used by [`PortControlBackend::Sysfs`]
+    pub fn set_sysfs(&mut self, mut sysfs: Sysfs) {
+        sysfs.set_dry_run(self.dry_run);
+        if let Some(companion) = self.companion.as_mut() {
+            companion.set_sysfs(sysfs.clone());
+        }
+        self.sysfs = sysfs;
+    }
+
+    /// Get the sysfs used by [`PortControlBackend::Sysfs`]
+    pub fn sysfs(&self) -> &Sysfs {
+        &self.sysfs
+    }
+
+    /// Log port power requests and sysfs writes instead of issuing them
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+        self.sysfs.set_dry_run(dry_run);
+        if let Some(companion) = self.companion.as_mut() {
+            companion.set_dry_run(dry_run);
+        }
+    }
+
+    /// Timeout of port status and port power requests
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Set the timeout of port status and port power requests
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+        if let Some(companion) = self.companion.as_mut() {
+            companion.set_timeout(timeout);
+        }
+    }
+
+    /// Set the companion hub, switched together with the hub
+    ///
+    /// The companion takes over the sysfs, timeout and dry run settings of the hub.
+    pub fn set_companion(&mut self, mut companion: Hub) {
+        companion.set_sysfs(self.sysfs.clone());
+        companion.set_timeout(self.timeout);
+        companion.set_dry_run(self.dry_run);
+        self.companion = Some(Box::new(companion));
+    }
+
+    /// Open the companion hub found through the sysfs peer links of the hub ports
+    ///
+    /// Nothing is done when no port has a peer, such as for hubs without companion.
+    pub fn attach_companion(&mut self, topology: &Topology, quirks: &Quirks) -> Result<(), Error> {
+        let location = self.location();
+        let Some(peer) = (1..=self.port_count())
+            .find_map(|port| self.sysfs.port_peer(&location, port).ok().flatten())
+        else {
+            return Ok(());
+        };
+        let info = topology.device(&peer.hub).ok_or_else(|| Error::NoDevice {
+            location: peer.hub.clone(),
+        })?;
+        let companion = Self::open(info, quirks, self.timeout)?;
+        self.set_companion(companion);
+        Ok(())
+    }
+
+    /// Get the companion hub
+    pub fn companion(&self) -> Option<&Hub> {
+        self.companion.as_deref()
+    }
+
+    /// Check if the quirk requires switching the companion hub together with the hub
+    pub fn companion_required(&self) -> bool {
+        self.quirk.as_ref().is_some_and(|q| q.companion_required)
+    }
+
     /// Get Hub port count
     pub fn port_count(&self) -> u8 {
         self.hub_descriptor.port_count()
     }
 
+    /// Get Hub descriptor
+    pub fn hub_descriptor(&self) -> &HubDescriptor {
+        &self.hub_descriptor
+    }
+
+    /// Get the quirk applying to the hub
+    pub fn quirk(&self) -> Option<&Quirk> {
+        self.quirk.as_ref()
+    }
+
+    /// Get the power switching mode of the hub, taking quirks into account
+    pub fn power_switching_mode(&self) -> LogicalPowerSwitchingMode {
+        if self.quirk.as_ref().is_some_and(|q| q.ganged) {
+            LogicalPowerSwitchingMode::Common
+        } else {
+            self.hub_descriptor.logical_power_switching_mode()
+        }
+    }
+
+    /// Get the physical label of a port, if it differs from the port number
+    pub fn port_label(&self, port: u8) -> Option<&str> {
+        self.quirk.as_ref()?.port_label(port)
+    }
+
     /// Get Hub container id
     pub fn container_id(&self) -> Option<ContainerId> {
-        self.container_id.clone()
+        self.bos_descriptor.as_ref()?.container_id()
+    }
+
+    /// Get Hub Binary device Object Store (BOS) descriptor, if the hub provides one
+    pub fn bos_descriptor(&self) -> Option<&BinaryObjectStoreDescriptor> {
+        self.bos_descriptor.as_ref()
+    }
+
+    fn check_port(&self, port: u8) -> Result<(), Error> {
+        let port_count = self.hub_descriptor.port_count();
+        if port == 0 || port > port_count {
+            Err(Error::InvalidPort {
+                location: self.location(),
+                port,
+                port_count,
+            })
+        } else {
+            Ok(())
+        }
     }
 
     /// Get Hub port status
     pub fn port_status(&self, port: u8) -> Result<PortStatus, Error> {
+        self.port_status_change(port).map(|(status, _)| status)
+    }
+
+    /// Get Hub port status together with the port change bits
+    pub fn port_status_change(&self, port: u8) -> Result<(PortStatus, PortChange), Error> {
         const STANDARD_REQUEST_GET_STATUS: u8 = 0x00;
 
-        if port > self.hub_descriptor.port_count() {
-            return Err(Error::InvalidPort);
+        self.check_port(port)?;
+
+        let context = RequestContext {
+            request: "GET_STATUS",
+            location: self.location(),
+            port: Some(port),
+        };
+        let mut buf = vec![0; 4];
+        let len = self
+            .device
+            .control_in_blocking(
+                Control {
+                    control_type: ControlType::Class,
+                    recipient: Recipient::Other,
+                    request: STANDARD_REQUEST_GET_STATUS,
+                    value: 0,
+                    index: (port as u16),
+                },
+                &mut buf,
+                self.timeout,
+            )
+            .map_err(|e| Error::transfer(context.clone(), e))?;
+        if len == 4 {
+            let port_status = u16::from_le_bytes([buf[0], buf[1]]);
+            let port_change = u16::from_le_bytes([buf[2], buf[3]]);
+            Ok((
+                PortStatus::from_field(port_status, self.super_speed),
+                PortChange::from_field(port_change, self.super_speed),
+            ))
+        } else {
+            Err(Error::InvalidResponse {
+                context,
+                expected: 4,
+                actual: len,
+            })
         }
+    }
+
+    /// Get Hub status
+    pub fn hub_status(&self) -> Result<HubStatus, Error> {
+        const STANDARD_REQUEST_GET_STATUS: u8 = 0x00;
 
+        let context = RequestContext {
+            request: "GET_STATUS(HUB)",
+            location: self.location(),
+            port: None,
+        };
         let mut buf = vec![0; 4];
-        let len = self.device.control_in_blocking(
-            Control {
-                control_type: ControlType::Class,
-                recipient: Recipient::Other,
-                request: STANDARD_REQUEST_GET_STATUS,
-                value: 0,
-                index: (port as u16),
-            },
-            &mut buf,
-            Duration::from_secs(5),
-        )?;
+        let len = self
+            .device
+            .control_in_blocking(
+                Control {
+                    control_type: ControlType::Class,
+                    recipient: Recipient::Device,
+                    request: STANDARD_REQUEST_GET_STATUS,
+                    value: 0,
+                    index: 0,
+                },
+                &mut buf,
+                self.timeout,
+            )
+            .map_err(|e| Error::transfer(context.clone(), e))?;
         if len == 4 {
-            let port_status = u16::from_le_bytes(buf[0..=1].try_into().unwrap());
-            let _port_change = u16::from_le_bytes(buf[2..=3].try_into().unwrap());
-            Ok(PortStatus::from_field(port_status, self.super_speed))
+            Ok(HubStatus {
+                status: u16::from_le_bytes([buf[0], buf[1]]),
+                change: u16::from_le_bytes([buf[2], buf[3]]),
+            })
         } else {
-            Err(Error::UsbTransferError(
-                nusb::transfer::TransferError::Fault,
-            ))
+            Err(Error::InvalidResponse {
+                context,
+                expected: 4,
+                actual: len,
+            })
         }
     }
 
-    /// Set port power
-    pub fn set_port_power(&self, port: u8, on: bool) -> Result<(), Error> {
-        if self.hub_descriptor.logical_power_switching_mode()
-            != LogicalPowerSwitchingMode::IndividualPort
-        {
-            return Err(Error::InvalidPort);
+    /// Switch port on or off using the given backend
+    ///
+    /// With [`PortControlBackend::Auto`] the sysfs `disable` attribute is used when the hub does
+    /// not support individual port power switching, or when the quirk marks the port as not
+    /// cutting VBUS. Other request failures are returned.
+    ///
+    /// When a companion hub is set, the peer port of the companion hub is switched first, once
+    /// the request to this hub is known to be possible. If switching this hub fails, the
+    /// companion is switched back. The peer port is read from sysfs, without sysfs the port with
+    /// the same number is used. Fails without companion hub when the quirk requires one.
+    pub fn switch_port(
+        &self,
+        port: u8,
+        on: bool,
+        backend: PortControlBackend,
+    ) -> Result<(), Error> {
+        if self.companion_required() && self.companion.is_none() {
+            return Err(Error::CompanionRequired {
+                location: self.location(),
+            });
+        }
+        if let Some(companion) = self.companion.as_ref() {
+            self.check_switch(port, on, backend)?;
+            let companion_port = match self.sysfs.port_peer(&self.location(), port) {
+                Ok(Some(peer)) if peer.hub == companion.location() => peer.port,
+                _ => port,
+            };
+            companion.switch_single_port(companion_port, on, backend)?;
+            if let Err(e) = self.switch_single_port(port, on, backend) {
+                let _ = companion.switch_single_port(companion_port, !on, backend);
+                return Err(e);
+            }
+            return Ok(());
+        }
+        self.switch_single_port(port, on, backend)
+    }
+
+    /// Check if switching a port with the given backend is possible, without switching it
+    fn check_switch(&self, port: u8, on: bool, backend: PortControlBackend) -> Result<(), Error> {
+        match backend.resolve(self.power_switching_mode(), self.quirk.as_ref(), port) {
+            PortControlBackend::Usb => self.check_port_power(port, on),
+            _ => self.check_port(port),
+        }
+    }
+
+    /// Switch port on or off using the given backend, without the companion hub
+    fn switch_single_port(
+        &self,
+        port: u8,
+        on: bool,
+        backend: PortControlBackend,
+    ) -> Result<(), Error> {
+        match backend.resolve(self.power_switching_mode(), self.quirk.as_ref(), port) {
+            PortControlBackend::Usb => self.set_port_power(port, on),
+            _ => {
+                self.check_port(port)?;
+                if backend == PortControlBackend::Auto {
+                    trace!(
+                        "Hub {} port {} switched through sysfs",
+                        self.location(),
+                        port
+                    );
+                }
+                self.sysfs.set_port_disabled(&self.location(), port, !on)
+            }
+        }
+    }
+
+    /// Check if a port is switched on using the given backend
+    ///
+    /// With [`PortControlBackend::Auto`] the sysfs `disable` attribute is read for the ports
+    /// which are switched through sysfs, see [`PortControlBackend::resolve`].
+    pub fn port_switched_on(&self, port: u8, backend: PortControlBackend) -> Result<bool, Error> {
+        match backend.resolve(self.power_switching_mode(), self.quirk.as_ref(), port) {
+            PortControlBackend::Usb => Ok(self.port_status(port)?.powered()),
+            _ => {
+                self.check_port(port)?;
+                Ok(!self.sysfs.port_disabled(&self.location(), port)?)
+            }
+        }
+    }
+
+    /// Check if setting port power is possible, see [`Hub::set_port_power`]
+    fn check_port_power(&self, port: u8, on: bool) -> Result<(), Error> {
+        let mode = self.power_switching_mode();
+        if mode != LogicalPowerSwitchingMode::IndividualPort {
+            return Err(Error::UnsupportedSwitchingMode {
+                location: self.location(),
+                mode,
+            });
         }
-        if port > self.hub_descriptor.port_count() {
-            return Err(Error::InvalidPort);
+        self.check_port(port)?;
+        if !on && self.quirk.as_ref().is_some_and(|q| q.no_vbus(port)) {
+            return Err(Error::NoVbusSwitching {
+                location: self.location(),
+                port,
+            });
         }
+        Ok(())
+    }
+
+    /// Set port power
+    ///
+    /// Fails for hubs without individual port power switching, including hubs which are
+    /// known to be ganged from their quirk, and when switching off a port which the quirk marks
+    /// as not cutting VBUS. Switching on is always allowed so that ports can be restored.
+    pub fn set_port_power(&self, port: u8, on: bool) -> Result<(), Error> {
+        self.check_port_power(port, on)?;
 
         const STANDARD_REQUEST_CLEAR_FEATURE: u8 = 0x01;
         const STANDARD_REQUEST_SET_FEATURE: u8 = 0x03;
         const USB_PORT_FEATURE_POWER: u16 = 0x0008;
 
-        let request = if on {
-            STANDARD_REQUEST_SET_FEATURE
+        let (request, request_name) = if on {
+            (STANDARD_REQUEST_SET_FEATURE, "SET_FEATURE(PORT_POWER)")
         } else {
-            STANDARD_REQUEST_CLEAR_FEATURE
+            (STANDARD_REQUEST_CLEAR_FEATURE, "CLEAR_FEATURE(PORT_POWER)")
+        };
+        let context = RequestContext {
+            request: request_name,
+            location: self.location(),
+            port: Some(port),
         };
 
         let buf = vec![];
+        let control = Control {
+            control_type: ControlType::Class,
+            recipient: Recipient::Other,
+            request,
+            value: USB_PORT_FEATURE_POWER,
+            index: (port as u16),
+        };
+
+        if self.dry_run {
+            info!(
+                "Dry run, {} {}:{} {}",
+                request_name,
+                context.location,
+                port,
+                SetupPacket::control_out(&control, &buf)
+            );
+            return Ok(());
+        }
 
         trace!("Set port power {}", if on { "on" } else { "off" });
 
-        let _ = self.device.control_out_blocking(
-            Control {
-                control_type: ControlType::Class,
-                recipient: Recipient::Other,
-                request,
-                value: USB_PORT_FEATURE_POWER,
-                index: (port as u16),
-            },
-            &buf,
-            Duration::from_secs(5),
-        )?;
+        let _ = self
+            .device
+            .control_out_blocking(control, &buf, self.timeout)
+            .map_err(|e| Error::transfer(context, e))?;
         Ok(())
     }
 }
@@ -228,7 +627,57 @@ impl Hash for Hub {
     }
 }
 
+/// Setup packet of a control request
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SetupPacket {
+    /// bmRequestType, direction, type and recipient
+    pub request_type: u8,
+    /// bRequest
+    pub request: u8,
+    /// wValue
+    pub value: u16,
+    /// wIndex
+    pub index: u16,
+    /// wLength
+    pub length: u16,
+}
+
+impl SetupPacket {
+    /// Setup packet of a host to device control request
+    pub fn control_out(control: &Control, data: &[u8]) -> Self {
+        let control_type = match control.control_type {
+            ControlType::Standard => 0x00,
+            ControlType::Class => 0x20,
+            ControlType::Vendor => 0x40,
+        };
+        let recipient = match control.recipient {
+            Recipient::Device => 0x00,
+            Recipient::Interface => 0x01,
+            Recipient::Endpoint => 0x02,
+            Recipient::Other => 0x03,
+        };
+        Self {
+            request_type: control_type | recipient,
+            request: control.request,
+            value: control.value,
+            index: control.index,
+            length: data.len() as u16,
+        }
+    }
+}
+
+impl std::fmt::Display for SetupPacket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "bmRequestType 0x{:02x} bRequest 0x{:02x} wValue 0x{:04x} wIndex 0x{:04x} wLength {}",
+            self.request_type, self.request, self.value, self.index, self.length
+        )
+    }
+}
+
 /// USB port status
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct PortStatus(pub u16);
 
 impl PortStatus {
@@ -237,6 +686,11 @@ impl PortStatus {
         PortStatus(value | if super_speed { Self::SUPER_SPEED } else { 0 })
     }
 
+    /// Field value as reported by the hub, without the SuperSpeed marker
+    pub fn field(&self) -> u16 {
+        self.0 & !Self::SUPER_SPEED
+    }
+
     /// This field reflects whether or not a device is currently connected to this port.
     #[inline(always)]
     pub fn connection(&self) -> bool {
@@ -297,8 +751,113 @@ impl PortStatus {
     const SUPER_SPEED: u16 = 0x8000;
 }
 
+/// USB port change, the port status bits which changed since they were last cleared
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PortChange(pub u16);
+
+impl PortChange {
+    /// Create port change from field value
+    pub fn from_field(value: u16, super_speed: bool) -> Self {
+        PortChange(value | if super_speed { Self::SUPER_SPEED } else { 0 })
+    }
+
+    /// Field value as reported by the hub, without the SuperSpeed marker
+    pub fn field(&self) -> u16 {
+        self.0 & !Self::SUPER_SPEED
+    }
+
+    /// Names of the changed bits
+    pub fn names(&self) -> Vec<&'static str> {
+        let bits: &[(u16, &'static str)] = if self.0 & Self::SUPER_SPEED == Self::SUPER_SPEED {
+            &[
+                (Self::CONNECTION, "connection"),
+                (Self::OVERCURRENT, "overcurrent"),
+                (Self::RESET, "reset"),
+                (Self::BH_RESET, "bh-reset"),
+                (Self::LINK_STATE, "link-state"),
+                (Self::CONFIG_ERROR, "config-error"),
+            ]
+        } else {
+            &[
+                (Self::CONNECTION, "connection"),
+                (Self::ENABLE, "enable"),
+                (Self::SUSPEND, "suspend"),
+                (Self::OVERCURRENT, "overcurrent"),
+                (Self::RESET, "reset"),
+            ]
+        };
+        bits.iter()
+            .filter(|(bit, _)| self.0 & bit == *bit)
+            .map(|(_, name)| *name)
+            .collect()
+    }
+
+    // USB hub port change
+    const CONNECTION: u16 = 0x0001;
+    const ENABLE: u16 = 0x0002;
+    const SUSPEND: u16 = 0x0004;
+    const OVERCURRENT: u16 = 0x0008;
+    const RESET: u16 = 0x0010;
+    // SuperSpeed hub port change
+    const BH_RESET: u16 = 0x0020;
+    const LINK_STATE: u16 = 0x0040;
+    const CONFIG_ERROR: u16 = 0x0080;
+    // Non-standard extension to the field
+    const SUPER_SPEED: u16 = 0x8000;
+}
+
+/// USB hub status and hub change
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HubStatus {
+    /// wHubStatus field
+    pub status: u16,
+    /// wHubChange field
+    pub change: u16,
+}
+
+impl HubStatus {
+    const LOCAL_POWER: u16 = 0x0001;
+    const OVERCURRENT: u16 = 0x0002;
+
+    /// Local power supply of the hub is lost
+    pub fn local_power_lost(&self) -> bool {
+        self.status & Self::LOCAL_POWER == Self::LOCAL_POWER
+    }
+
+    /// The hub reports a global over-current condition
+    pub fn overcurrent(&self) -> bool {
+        self.status & Self::OVERCURRENT == Self::OVERCURRENT
+    }
+
+    /// Local power status changed
+    pub fn local_power_changed(&self) -> bool {
+        self.change & Self::LOCAL_POWER == Self::LOCAL_POWER
+    }
+
+    /// Over-current status changed
+    pub fn overcurrent_changed(&self) -> bool {
+        self.change & Self::OVERCURRENT == Self::OVERCURRENT
+    }
+}
+
+/// Port status is serialized as the raw field value together with the decoded flags
+impl Serialize for PortStatus {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("PortStatus", 7)?;
+        state.serialize_field("raw", &self.field())?;
+        state.serialize_field("connection", &self.connection())?;
+        state.serialize_field("enabled", &self.enabled())?;
+        state.serialize_field("suspended", &self.suspended())?;
+        state.serialize_field("overcurrent", &self.overcurrent())?;
+        state.serialize_field("reset", &self.reset())?;
+        state.serialize_field("powered", &self.powered())?;
+        state.end()
+    }
+}
+
 /// Logical Power Switching Mode
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum LogicalPowerSwitchingMode {
     /// Unknown
     None,
@@ -308,14 +867,99 @@ pub enum LogicalPowerSwitchingMode {
     IndividualPort,
 }
 
+/// Over-current Protection Mode
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverCurrentProtectionMode {
+    /// Over-current reported for all ports together
+    Global,
+    /// Over-current reported for each port
+    IndividualPort,
+    /// No over-current protection
+    None,
+}
+
 /// USB hub descriptor
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct HubDescriptor {
     port_count: u8,
     characteristics: u16,
+    super_speed: bool,
+    power_on_to_power_good: u8,
+    controller_current: u8,
+    device_removable: Option<u16>,
 }
 
 impl HubDescriptor {
+    const DESCRIPTOR_TYPE_HUB: u8 = 0x29;
+    const DESCRIPTOR_TYPE_SUPERSPEED_HUB: u8 = 0x2a;
+
+    /// Parse a hub or SuperSpeed hub descriptor
+    ///
+    /// Only the fixed part of the descriptor is required, trailing data is ignored.
+    pub fn from_data(data: &[u8]) -> Result<Self, ParseError> {
+        const DESCRIPTOR: &str = "Hub";
+        const MIN_LENGTH: usize = 7;
+
+        if data.len() < MIN_LENGTH {
+            return Err(ParseError::TooShort {
+                descriptor: DESCRIPTOR,
+                required: MIN_LENGTH,
+                actual: data.len(),
+            });
+        }
+        if usize::from(data[0]) < MIN_LENGTH {
+            return Err(ParseError::InvalidLength {
+                descriptor: DESCRIPTOR,
+                length: usize::from(data[0]),
+            });
+        }
+        if data[1] != Self::DESCRIPTOR_TYPE_HUB && data[1] != Self::DESCRIPTOR_TYPE_SUPERSPEED_HUB {
+            return Err(ParseError::InvalidDescriptorType {
+                descriptor: DESCRIPTOR,
+                expected: Self::DESCRIPTOR_TYPE_HUB,
+                actual: data[1],
+            });
+        }
+
+        let port_count = if data[2] <= 15 { data[2] } else { 0 };
+        let characteristics = u16::from_le_bytes([data[3], data[4]]);
+        let super_speed = data[1] == Self::DESCRIPTOR_TYPE_SUPERSPEED_HUB;
+
+        // DeviceRemovable follows the fixed part, after the SuperSpeed header decode latency
+        // and hub delay fields
+        let data = &data[..data.len().min(usize::from(data[0]))];
+        let device_removable = match super_speed {
+            true => data.get(10..12),
+            false => data.get(7..7 + usize::from(port_count) / 8 + 1),
+        }
+        .map(|bytes| {
+            bytes
+                .iter()
+                .rev()
+                .fold(0u16, |bits, byte| (bits << 8) | u16::from(*byte))
+        });
+
+        Ok(Self {
+            port_count,
+            characteristics,
+            super_speed,
+            power_on_to_power_good: data[5],
+            controller_current: data[6],
+            device_removable,
+        })
+    }
+
+    /// Check if the descriptor is a SuperSpeed hub descriptor
+    pub fn super_speed(&self) -> bool {
+        self.super_speed
+    }
+
+    /// Raw wHubCharacteristics field
+    pub fn characteristics(&self) -> u16 {
+        self.characteristics
+    }
+
     /// Number of USB hub ports
     pub fn port_count(&self) -> u8 {
         self.port_count
@@ -333,105 +977,74 @@ impl HubDescriptor {
             _ => LogicalPowerSwitchingMode::None,
         }
     }
-}
 
-#[derive(Debug, PartialEq)]
-enum DeviceCapabilityType {
-    WirelessUsb,
-    Usb20Extension,
-    SuperSpeedUsb,
-    ContainerId,
-    Platform,
-    PowerDeliveryCapability,
-    BatteryInfoCapability,
-    PowerDeliveryConsumerPortCapability,
-    PowerDeliveryProviderPortCapability,
-    SuperSpeedPlus,
-    PrecisionTimeMeasurement,
-    WirelessUsbExtensions,
-    Billboard,
-    Authentication,
-    BillboardExtensions,
-    ConfigurationSummary,
-    FwStatus,
-    Reserved,
-}
+    /// Check if the hub is part of a compound device
+    pub fn compound_device(&self) -> bool {
+        const HUB_CHARACTERISTICS_COMPOUND: u16 = 0x0004;
+        self.characteristics & HUB_CHARACTERISTICS_COMPOUND == HUB_CHARACTERISTICS_COMPOUND
+    }
+
+    /// Over-current Protection Mode of the hub
+    pub fn over_current_protection_mode(&self) -> OverCurrentProtectionMode {
+        const HUB_CHARACTERISTICS_OCPM_MASK: u16 = 0x0018;
+        const HUB_CHARACTERISTICS_OCPM_GLOBAL: u16 = 0x0000;
+        const HUB_CHARACTERISTICS_OCPM_INDIVIDUAL_PORT: u16 = 0x0008;
 
-impl From<u8> for DeviceCapabilityType {
-    fn from(value: u8) -> Self {
-        match value {
-            0x01 => Self::WirelessUsb,
-            0x02 => Self::Usb20Extension,
-            0x03 => Self::SuperSpeedUsb,
-            0x04 => Self::ContainerId,
-            0x05 => Self::Platform,
-            0x06 => Self::PowerDeliveryCapability,
-            0x07 => Self::BatteryInfoCapability,
-            0x08 => Self::PowerDeliveryConsumerPortCapability,
-            0x09 => Self::PowerDeliveryProviderPortCapability,
-            0x0a => Self::SuperSpeedPlus,
-            0x0b => Self::PrecisionTimeMeasurement,
-            0x0c => Self::WirelessUsbExtensions,
-            0x0d => Self::Billboard,
-            0x0e => Self::Authentication,
-            0x0f => Self::BillboardExtensions,
-            0x10 => Self::ConfigurationSummary,
-            0x11 => Self::FwStatus,
-            _ => Self::Reserved,
+        match self.characteristics & HUB_CHARACTERISTICS_OCPM_MASK {
+            HUB_CHARACTERISTICS_OCPM_GLOBAL => OverCurrentProtectionMode::Global,
+            HUB_CHARACTERISTICS_OCPM_INDIVIDUAL_PORT => OverCurrentProtectionMode::IndividualPort,
+            _ => OverCurrentProtectionMode::None,
         }
     }
-}
 
-/// Container Id
-#[derive(Clone, PartialEq)]
-pub struct ContainerId(pub [u8; 16]);
+    /// Transaction Translator think time in full-speed bit times, `None` for SuperSpeed hubs
+    pub fn tt_think_time(&self) -> Option<u8> {
+        const HUB_CHARACTERISTICS_TTTT_SHIFT: u16 = 5;
+        const HUB_CHARACTERISTICS_TTTT_MASK: u16 = 0x0003;
 
-const BOS_MAX: usize = 256;
+        if self.super_speed {
+            return None;
+        }
+        let think_time = (self.characteristics >> HUB_CHARACTERISTICS_TTTT_SHIFT)
+            & HUB_CHARACTERISTICS_TTTT_MASK;
+        Some((think_time as u8 + 1) * 8)
+    }
 
-/// USB hub descriptor
-#[derive(Clone, PartialEq)]
-pub struct BinaryObjectStoreDescriptor {
-    data: [u8; BOS_MAX],
-    length: usize,
-}
+    /// Check if the hub supports port indicators, always `false` for SuperSpeed hubs
+    pub fn port_indicators(&self) -> bool {
+        const HUB_CHARACTERISTICS_PORT_INDICATORS: u16 = 0x0080;
+        !self.super_speed
+            && self.characteristics & HUB_CHARACTERISTICS_PORT_INDICATORS
+                == HUB_CHARACTERISTICS_PORT_INDICATORS
+    }
 
-impl BinaryObjectStoreDescriptor {
-    /// Create BOS
-    pub fn from_data(data: &[u8]) -> Self {
-        assert!(data.len() >= 5);
-        assert!(data[0] == 5);
-        assert!(data[1] == 0x0f);
-        let total = u16::from_le_bytes(data[2..=3].try_into().unwrap());
-        assert!(usize::from(total) == data.len());
-
-        let mut bos = BinaryObjectStoreDescriptor {
-            data: [0; BOS_MAX],
-            length: data.len(),
-        };
-        bos.data[..bos.length].copy_from_slice(data);
-        bos
+    /// Time from switching on port power until the power is good
+    pub fn power_good_time(&self) -> Duration {
+        Duration::from_millis(u64::from(self.power_on_to_power_good) * 2)
     }
 
-    /// Get container id
-    pub fn container_id(&self) -> Option<ContainerId> {
-        let buf = &self.data[..self.length];
-        let count = buf[4];
-
-        let mut part = &buf[5..];
-
-        for _ in 0..count {
-            const DESCRIPTOR_TYPE_DEVICE_CAPABILITY: u8 = 0x10;
-            let length = part[0];
-            let descriptor_type = part[1];
-            assert!(descriptor_type == DESCRIPTOR_TYPE_DEVICE_CAPABILITY);
-            let device_capability_type = DeviceCapabilityType::from(part[2]);
-            if device_capability_type == DeviceCapabilityType::ContainerId && length == 20 {
-                let mut cid = [0u8; 16];
-                cid.copy_from_slice(&buf[4..20]);
-                return Some(ContainerId(cid));
-            }
-            part = &part[usize::from(length)..];
+    /// Maximum current drawn by the hub controller, in milliamperes
+    ///
+    /// SuperSpeed hubs report the current in units of 4 mA.
+    pub fn controller_current(&self) -> u16 {
+        let current = u16::from(self.controller_current);
+        if self.super_speed {
+            current * 4
+        } else {
+            current
+        }
+    }
+
+    /// Check if the device attached to a port is removable
+    ///
+    /// `None` if the descriptor does not include the DeviceRemovable field or the port does
+    /// not exist.
+    pub fn port_removable(&self, port: u8) -> Option<bool> {
+        if port == 0 || port > self.port_count {
+            return None;
         }
-        None
+        // A set bit marks a non-removable device
+        self.device_removable
+            .map(|removable| removable & (1 << port) == 0)
     }
 }
@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::{Hub, Location, PortLocation, PortStatus};
+
+/// Change of a hub or hub port, found by comparing two snapshots
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// Hub appeared
+    HubAdded {
+        /// Hub location
+        hub: Location,
+        /// Vendor ID
+        vendor_id: u16,
+        /// Product ID
+        product_id: u16,
+    },
+    /// Hub disappeared
+    HubRemoved {
+        /// Hub location
+        hub: Location,
+    },
+    /// Device connected to a port
+    Connected {
+        /// Hub port
+        port: PortLocation,
+    },
+    /// Device disconnected from a port
+    Disconnected {
+        /// Hub port
+        port: PortLocation,
+    },
+    /// Port power switched on
+    PoweredOn {
+        /// Hub port
+        port: PortLocation,
+    },
+    /// Port power switched off
+    PoweredOff {
+        /// Hub port
+        port: PortLocation,
+    },
+    /// Over-current detected on a port
+    OverCurrent {
+        /// Hub port
+        port: PortLocation,
+    },
+    /// Over-current condition of a port cleared
+    OverCurrentCleared {
+        /// Hub port
+        port: PortLocation,
+    },
+}
+
+impl std::fmt::Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::HubAdded {
+                hub,
+                vendor_id,
+                product_id,
+            } => write!(f, "hub {} added {:04x}:{:04x}", hub, vendor_id, product_id),
+            Self::HubRemoved { hub } => write!(f, "hub {} removed", hub),
+            Self::Connected { port } => write!(f, "port {} connected", port),
+            Self::Disconnected { port } => write!(f, "port {} disconnected", port),
+            Self::PoweredOn { port } => write!(f, "port {} powered on", port),
+            Self::PoweredOff { port } => write!(f, "port {} powered off", port),
+            Self::OverCurrent { port } => write!(f, "port {} over-current", port),
+            Self::OverCurrentCleared { port } => write!(f, "port {} over-current cleared", port),
+        }
+    }
+}
+
+/// Port status of a hub at one point in time
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HubSnapshot {
+    /// Vendor ID
+    pub vendor_id: u16,
+    /// Product ID
+    pub product_id: u16,
+    /// Status of the ports which could be read
+    pub ports: BTreeMap<u8, PortStatus>,
+}
+
+impl HubSnapshot {
+    /// Read the status of all hub ports, ports which fail to read are left out
+    pub fn read(hub: &Hub) -> Self {
+        let info = hub.info();
+        let ports = (1..=hub.port_count())
+            .filter_map(|port| hub.port_status(port).ok().map(|status| (port, status)))
+            .collect();
+        Self {
+            vendor_id: info.vendor_id(),
+            product_id: info.product_id(),
+            ports,
+        }
+    }
+}
+
+/// Port status of a set of hubs at one point in time
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Snapshot {
+    hubs: BTreeMap<Location, HubSnapshot>,
+}
+
+impl Snapshot {
+    /// Read the port status of the hubs
+    pub fn read<'a, I: IntoIterator<Item = &'a Hub>>(hubs: I) -> Self {
+        let hubs = hubs
+            .into_iter()
+            .map(|hub| (hub.location(), HubSnapshot::read(hub)))
+            .collect();
+        Self { hubs }
+    }
+
+    /// Add a hub, replacing an existing hub at the same location
+    pub fn insert(&mut self, location: Location, hub: HubSnapshot) {
+        self.hubs.insert(location, hub);
+    }
+
+    /// Get the hub at the location
+    pub fn get(&self, location: &Location) -> Option<&HubSnapshot> {
+        self.hubs.get(location)
+    }
+
+    /// Events leading from this snapshot to a later snapshot
+    ///
+    /// Ports missing from either snapshot, such as ports which failed to read, are not compared.
+    pub fn changes(&self, later: &Snapshot) -> Vec<Event> {
+        let mut events = Vec::new();
+        for location in self.hubs.keys() {
+            if !later.hubs.contains_key(location) {
+                events.push(Event::HubRemoved {
+                    hub: location.clone(),
+                });
+            }
+        }
+        for (location, hub) in later.hubs.iter() {
+            let Some(earlier) = self.hubs.get(location) else {
+                events.push(Event::HubAdded {
+                    hub: location.clone(),
+                    vendor_id: hub.vendor_id,
+                    product_id: hub.product_id,
+                });
+                continue;
+            };
+            for (port, status) in hub.ports.iter() {
+                let Some(previous) = earlier.ports.get(port) else {
+                    continue;
+                };
+                let port = PortLocation::new(location.clone(), *port);
+                if previous.powered() != status.powered() {
+                    events.push(match status.powered() {
+                        true => Event::PoweredOn { port: port.clone() },
+                        false => Event::PoweredOff { port: port.clone() },
+                    });
+                }
+                if previous.connection() != status.connection() {
+                    events.push(match status.connection() {
+                        true => Event::Connected { port: port.clone() },
+                        false => Event::Disconnected { port: port.clone() },
+                    });
+                }
+                if previous.overcurrent() != status.overcurrent() {
+                    events.push(match status.overcurrent() {
+                        true => Event::OverCurrent { port },
+                        false => Event::OverCurrentCleared { port },
+                    });
+                }
+            }
+        }
+        events
+    }
+}
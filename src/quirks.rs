@@ -0,0 +1,252 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+/// Known behaviour of a hub model which differs from what the hub reports
+///
+/// Quirks are stored in TOML as an array of `quirk` tables, in the quirks file as well as in
+/// the configuration file, for example
+///
+/// ```toml
+/// [[quirk]]
+/// vendor_id = 0x2109
+/// product_id = 0x3431
+/// ganged = true
+/// port_labels = ["USB2 top left", "USB2 bottom left"]
+/// ```
+///
+/// Quirks files written by earlier versions use `hub` tables, which are still read.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Quirk {
+    /// Vendor ID of the hub
+    pub vendor_id: u16,
+    /// Product ID of the hub
+    pub product_id: u16,
+    /// Lowest device release number (bcdDevice) the quirk applies to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_version_min: Option<u16>,
+    /// Highest device release number (bcdDevice) the quirk applies to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_version_max: Option<u16>,
+    /// Hub reports individual port power switching, but switches all ports together
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub ganged: bool,
+    /// Ports which do not cut VBUS when power is switched off
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub no_vbus_ports: Vec<u8>,
+    /// Physical port labels, the first label belongs to port 1
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub port_labels: Vec<String>,
+    /// The USB 2 and USB 3 companion hubs have to be switched together to cut power
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub companion_required: bool,
+    /// Hub returns an invalid BOS descriptor, or hangs when it is requested
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub broken_bos: bool,
+    /// Free form description
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl Quirk {
+    /// Check if the quirk applies to a hub
+    pub fn matches(&self, vendor_id: u16, product_id: u16, device_version: u16) -> bool {
+        self.vendor_id == vendor_id
+            && self.product_id == product_id
+            && self
+                .device_version_min
+                .is_none_or(|min| device_version >= min)
+            && self
+                .device_version_max
+                .is_none_or(|max| device_version <= max)
+    }
+
+    /// Physical label of a port, if it differs from the port number
+    pub fn port_label(&self, port: u8) -> Option<&str> {
+        let index = usize::from(port.checked_sub(1)?);
+        self.port_labels.get(index).map(|label| label.as_str())
+    }
+
+    /// Check if switching power of a port is known not to cut VBUS
+    pub fn no_vbus(&self, port: u8) -> bool {
+        self.no_vbus_ports.contains(&port)
+    }
+
+    /// Add a quirk for the same hub which takes precedence
+    ///
+    /// Flags set by either quirk are kept, the port lists, labels and note of `later` replace
+    /// those of this quirk when they are set.
+    fn merge(&mut self, later: &Quirk) {
+        self.device_version_min = later.device_version_min;
+        self.device_version_max = later.device_version_max;
+        self.ganged |= later.ganged;
+        if !later.no_vbus_ports.is_empty() {
+            self.no_vbus_ports = later.no_vbus_ports.clone();
+        }
+        if !later.port_labels.is_empty() {
+            self.port_labels = later.port_labels.clone();
+        }
+        self.companion_required |= later.companion_required;
+        self.broken_bos |= later.broken_bos;
+        if later.note.is_some() {
+            self.note = later.note.clone();
+        }
+    }
+
+    /// Short names of the quirks which are set
+    pub fn names(&self) -> Vec<&'static str> {
+        let mut names = Vec::new();
+        if self.ganged {
+            names.push("ganged");
+        }
+        if !self.no_vbus_ports.is_empty() {
+            names.push("no-vbus");
+        }
+        if self.companion_required {
+            names.push("companion");
+        }
+        if self.broken_bos {
+            names.push("broken-bos");
+        }
+        names
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct QuirksFile {
+    #[serde(default, alias = "hub")]
+    quirk: Vec<Quirk>,
+}
+
+/// Table of hub quirks
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Quirks {
+    entries: Vec<Quirk>,
+}
+
+impl Quirks {
+    /// Quirks of hubs known to misbehave
+    pub fn builtin() -> Self {
+        let entries = vec![
+            Quirk {
+                vendor_id: 0x0424,
+                product_id: 0x9514,
+                ganged: true,
+                note: Some(String::from("SMSC LAN9514 in Raspberry Pi B+, 2B and 3B")),
+                ..Default::default()
+            },
+            Quirk {
+                vendor_id: 0x2109,
+                product_id: 0x3431,
+                ganged: true,
+                companion_required: true,
+                note: Some(String::from("VIA VL805 USB 2 hub in Raspberry Pi 4B")),
+                ..Default::default()
+            },
+        ];
+        Self { entries }
+    }
+
+    /// Parse quirks from TOML
+    pub fn from_toml(toml: &str) -> Result<Self, Error> {
+        let file = toml::from_str::<QuirksFile>(toml).map_err(|e| Error::InvalidQuirks {
+            path: None,
+            message: e.to_string(),
+        })?;
+        Ok(Self {
+            entries: file.quirk,
+        })
+    }
+
+    /// Load quirks from a TOML file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let toml = std::fs::read_to_string(path).map_err(|source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_toml(&toml).map_err(|e| match e {
+            Error::InvalidQuirks { message, .. } => Error::InvalidQuirks {
+                path: Some(path.to_path_buf()),
+                message,
+            },
+            e => e,
+        })
+    }
+
+    /// Write quirks to a TOML file, creating the parent directory if needed
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let io_error = |source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(io_error)?;
+        }
+        std::fs::write(path, self.to_toml()).map_err(io_error)
+    }
+
+    /// Format quirks as TOML
+    pub fn to_toml(&self) -> String {
+        let file = QuirksFile {
+            quirk: self.entries.clone(),
+        };
+        toml::to_string(&file).expect("quirks serialize to TOML")
+    }
+
+    /// Add a quirk, taking precedence over the existing quirks for the same hub
+    pub fn insert(&mut self, quirk: Quirk) {
+        self.entries.push(quirk);
+    }
+
+    /// Get the quirk for all releases of a hub, adding an empty quirk if there is none
+    pub fn entry(&mut self, vendor_id: u16, product_id: u16) -> &mut Quirk {
+        let position = self.entries.iter().rposition(|q| {
+            q.vendor_id == vendor_id
+                && q.product_id == product_id
+                && q.device_version_min.is_none()
+                && q.device_version_max.is_none()
+        });
+        let index = match position {
+            Some(index) => index,
+            None => {
+                self.entries.push(Quirk {
+                    vendor_id,
+                    product_id,
+                    ..Default::default()
+                });
+                self.entries.len() - 1
+            }
+        };
+        &mut self.entries[index]
+    }
+
+    /// Add quirks, taking precedence over the existing quirks for the same hub
+    pub fn extend(&mut self, quirks: Quirks) {
+        self.entries.extend(quirks.entries);
+    }
+
+    /// Iterate over all quirks
+    pub fn iter(&self) -> impl Iterator<Item = &Quirk> {
+        self.entries.iter()
+    }
+
+    /// Find the quirk for a hub, merging all matching quirks
+    ///
+    /// Quirks added later take precedence, see [`Quirks::insert`], but only replace what they
+    /// set. A local quirk adding port labels keeps the flags of the built-in quirk.
+    pub fn lookup(&self, vendor_id: u16, product_id: u16, device_version: u16) -> Option<Quirk> {
+        let mut matching = self
+            .entries
+            .iter()
+            .filter(|q| q.matches(vendor_id, product_id, device_version));
+        let mut quirk = matching.next()?.clone();
+        for later in matching {
+            quirk.merge(later);
+        }
+        Some(quirk)
+    }
+}
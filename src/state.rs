@@ -0,0 +1,192 @@
+use nusb::{DeviceInfo, Speed};
+use serde::Serialize;
+
+use crate::{
+    ContainerId, Hub, Location, LogicalPowerSwitchingMode, PortStatus, Quirks, Sysfs, SysfsDevice,
+    SysfsHub, Topology,
+};
+
+/// Name of a USB speed
+pub(crate) fn speed_name(speed: Speed) -> &'static str {
+    match speed {
+        Speed::Low => "low",
+        Speed::Full => "full",
+        Speed::High => "high",
+        Speed::Super => "super",
+        Speed::SuperPlus => "super_plus",
+        _ => "unknown",
+    }
+}
+
+/// Identification of a USB device
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DeviceSummary {
+    /// Device location
+    pub location: Location,
+    /// Bus number
+    pub busnum: u8,
+    /// Device address on the bus, `None` if sysfs does not provide it
+    pub address: Option<u8>,
+    /// Vendor ID
+    pub vendor_id: u16,
+    /// Product ID
+    pub product_id: u16,
+    /// Device class
+    pub class: u8,
+    /// Device subclass
+    pub subclass: u8,
+    /// Device protocol
+    pub protocol: u8,
+    /// Device release number, BCD encoded
+    pub device_version: u16,
+    /// Connection speed, such as `high` or `super`
+    pub speed: Option<&'static str>,
+    /// Manufacturer string
+    pub manufacturer: Option<String>,
+    /// Product string
+    pub product: Option<String>,
+    /// Serial number string
+    pub serial_number: Option<String>,
+}
+
+impl DeviceSummary {
+    /// Summarize a device
+    pub fn from_device_info(info: &DeviceInfo) -> Self {
+        Self {
+            location: Location::from_device_info(info),
+            busnum: info.busnum(),
+            address: Some(info.device_address()),
+            vendor_id: info.vendor_id(),
+            product_id: info.product_id(),
+            class: info.class(),
+            subclass: info.subclass(),
+            protocol: info.protocol(),
+            device_version: info.device_version(),
+            speed: info.speed().map(speed_name),
+            manufacturer: info.manufacturer_string().map(String::from),
+            product: info.product_string().map(String::from),
+            serial_number: info.serial_number().map(String::from),
+        }
+    }
+
+    /// Summarize a device read from sysfs
+    pub fn from_sysfs(device: &SysfsDevice) -> Self {
+        Self {
+            location: device.location.clone(),
+            busnum: device.location.busnum(),
+            address: device.address,
+            vendor_id: device.vendor_id,
+            product_id: device.product_id,
+            class: device.class,
+            subclass: device.subclass,
+            protocol: device.protocol,
+            device_version: device.device_version,
+            speed: device.speed,
+            manufacturer: device.manufacturer.clone(),
+            product: device.product.clone(),
+            serial_number: device.serial_number.clone(),
+        }
+    }
+}
+
+/// State of a hub port
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PortState {
+    /// Port number
+    pub port: u8,
+    /// Physical port label from the hub quirk
+    pub label: Option<String>,
+    /// Port status, `None` if reading the status failed
+    pub status: Option<PortStatus>,
+    /// Error reading the port status
+    pub error: Option<String>,
+    /// Device attached to the port
+    pub device: Option<DeviceSummary>,
+}
+
+/// State of a hub and all its ports
+///
+/// Hubs attached to the ports are only summarized, each hub has its own state.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct HubState {
+    /// Hub device
+    #[serde(flatten)]
+    pub device: DeviceSummary,
+    /// Number of hub ports
+    pub port_count: u8,
+    /// Power switching mode, taking quirks into account, `None` when read from sysfs
+    pub switching_mode: Option<LogicalPowerSwitchingMode>,
+    /// Container id
+    pub container_id: Option<ContainerId>,
+    /// Names of the quirks applying to the hub
+    pub quirks: Vec<&'static str>,
+    /// Hub ports
+    pub ports: Vec<PortState>,
+}
+
+impl HubState {
+    /// Read the state of a hub and its ports, devices are looked up in the topology
+    pub fn read(hub: &Hub, topology: &Topology) -> Self {
+        let location = hub.location();
+        let ports = (1..=hub.port_count())
+            .map(|port| Self::read_port(hub, port, topology.device(&location.child(port))))
+            .collect();
+        Self {
+            device: DeviceSummary::from_device_info(&hub.info()),
+            port_count: hub.port_count(),
+            switching_mode: Some(hub.power_switching_mode()),
+            container_id: hub.container_id(),
+            quirks: hub.quirk().map(|q| q.names()).unwrap_or_default(),
+            ports,
+        }
+    }
+
+    /// State of a hub read from sysfs, without opening the hub
+    ///
+    /// The port status, switching mode and container id are not available from sysfs and
+    /// left out. Devices attached to the ports are read from sysfs as well.
+    pub fn from_sysfs(hub: &SysfsHub, sysfs: &Sysfs, quirks: &Quirks) -> Self {
+        let device = &hub.device;
+        let quirk = quirks.lookup(device.vendor_id, device.product_id, device.device_version);
+        let ports = hub
+            .ports
+            .iter()
+            .map(|port| PortState {
+                port: port.port,
+                label: quirk
+                    .as_ref()
+                    .and_then(|q| q.port_label(port.port))
+                    .map(String::from),
+                status: None,
+                error: None,
+                device: sysfs
+                    .device(&device.location.child(port.port))
+                    .ok()
+                    .map(|d| DeviceSummary::from_sysfs(&d)),
+            })
+            .collect();
+        Self {
+            device: DeviceSummary::from_sysfs(device),
+            port_count: hub.port_count(),
+            switching_mode: None,
+            container_id: None,
+            quirks: quirk.map(|q| q.names()).unwrap_or_default(),
+            ports,
+        }
+    }
+
+    /// Read the state of a single port
+    pub fn read_port(hub: &Hub, port: u8, device: Option<&DeviceInfo>) -> PortState {
+        let (status, error) = match hub.port_status(port) {
+            Ok(status) => (Some(status), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        PortState {
+            port,
+            label: hub.port_label(port).map(String::from),
+            status,
+            error,
+            device: device.map(DeviceSummary::from_device_info),
+        }
+    }
+}
@@ -0,0 +1,379 @@
+use std::path::{Path, PathBuf};
+
+use log::{info, trace};
+use serde::Serialize;
+
+use crate::topology::parse_digits;
+use crate::{DEVICE_CLASS_HUB, Error, Location, ParseError, PortLocation, RequestContext};
+
+/// Default root of the sysfs file system
+pub const SYSFS_ROOT: &str = "/sys";
+
+/// USB device information read from sysfs
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SysfsDevice {
+    /// Device location
+    pub location: Location,
+    /// Device address on the bus
+    pub address: Option<u8>,
+    /// Connection speed, such as `high` or `super`
+    pub speed: Option<&'static str>,
+    /// USB specification version, BCD encoded
+    pub usb_version: u16,
+    /// Device class
+    pub class: u8,
+    /// Device subclass
+    pub subclass: u8,
+    /// Device protocol
+    pub protocol: u8,
+    /// Vendor ID
+    pub vendor_id: u16,
+    /// Product ID
+    pub product_id: u16,
+    /// Device release number, BCD encoded
+    pub device_version: u16,
+    /// Manufacturer string
+    pub manufacturer: Option<String>,
+    /// Product string
+    pub product: Option<String>,
+    /// Serial number string
+    pub serial_number: Option<String>,
+}
+
+impl SysfsDevice {
+    /// Check if the device is a hub
+    pub fn is_hub(&self) -> bool {
+        self.class == DEVICE_CLASS_HUB
+    }
+}
+
+/// Hub port information read from sysfs
+///
+/// Attributes not provided by the running kernel are `None`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SysfsPort {
+    /// Port number
+    pub port: u8,
+    /// State of the attached device, such as `not attached` or `configured`
+    pub state: Option<String>,
+    /// Connection type, such as `hotplug` or `hardwired`
+    pub connect_type: Option<String>,
+    /// Number of over-current conditions seen on the port
+    pub over_current_count: Option<u32>,
+    /// Port is logically disabled
+    pub disabled: Option<bool>,
+}
+
+impl SysfsPort {
+    /// Check if a device is attached to the port
+    pub fn connected(&self) -> bool {
+        self.state
+            .as_deref()
+            .is_some_and(|state| state != "not attached")
+    }
+}
+
+/// Hub information read from sysfs
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SysfsHub {
+    /// Hub device
+    pub device: SysfsDevice,
+    /// Hub ports
+    pub ports: Vec<SysfsPort>,
+}
+
+impl SysfsHub {
+    /// Number of hub ports
+    pub fn port_count(&self) -> u8 {
+        self.ports.len() as u8
+    }
+}
+
+/// Name of a USB speed from the sysfs `speed` attribute in Mbit/s
+fn speed_name(speed: &str) -> Option<&'static str> {
+    match speed {
+        "1.5" => Some("low"),
+        "12" => Some("full"),
+        "480" => Some("high"),
+        "5000" => Some("super"),
+        "10000" | "20000" => Some("super_plus"),
+        _ => None,
+    }
+}
+
+/// Access to USB hubs and ports through the Linux sysfs file system
+///
+/// The root is configurable so that a fake directory tree can be used in place of `/sys`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sysfs {
+    root: PathBuf,
+    dry_run: bool,
+}
+
+impl Default for Sysfs {
+    fn default() -> Self {
+        Self::new(SYSFS_ROOT)
+    }
+}
+
+impl Sysfs {
+    /// Create sysfs access with the given root
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            root: root.into(),
+            dry_run: false,
+        }
+    }
+
+    /// Log attribute writes instead of writing them
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+
+    /// Root of the sysfs file system
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Directory of the device at a location
+    pub fn device_path(&self, location: &Location) -> PathBuf {
+        self.root.join("bus/usb/devices").join(location.to_string())
+    }
+
+    /// Directory of the first interface of the hub at a location
+    pub fn hub_interface_path(&self, hub: &Location) -> PathBuf {
+        let interface = if hub.port_chain().is_empty() {
+            format!("{}-0:1.0", hub.busnum())
+        } else {
+            format!("{}:1.0", hub)
+        };
+        self.root.join("bus/usb/devices").join(interface)
+    }
+
+    /// Directory of a hub port
+    pub fn port_path(&self, hub: &Location, port: u8) -> PathBuf {
+        self.hub_interface_path(hub)
+            .join(format!("{}-port{}", hub, port))
+    }
+
+    /// Check if a hub port is logically disabled
+    pub fn port_disabled(&self, hub: &Location, port: u8) -> Result<bool, Error> {
+        let path = self.port_path(hub, port).join("disable");
+        Ok(self.read_attribute(&path)? == "1")
+    }
+
+    /// Logically disable or enable a hub port
+    ///
+    /// Disabling a port disconnects the attached device even if the hub cannot switch port power.
+    pub fn set_port_disabled(&self, hub: &Location, port: u8, disabled: bool) -> Result<(), Error> {
+        let path = self.port_path(hub, port).join("disable");
+        trace!("Set {} port {} disable {}", hub, port, disabled);
+        self.write_attribute(&path, if disabled { "1" } else { "0" })
+    }
+
+    /// Peer of a hub port, the port of the companion hub sharing the physical connector
+    ///
+    /// `None` if the port has no peer.
+    pub fn port_peer(&self, hub: &Location, port: u8) -> Result<Option<PortLocation>, Error> {
+        let path = self.port_path(hub, port).join("peer");
+        let target = match std::fs::read_link(&path) {
+            Ok(target) => target,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(source) => return Err(Error::Io { path, source }),
+        };
+        // The link points at the peer port directory, named `<hub>-port<port>`
+        let invalid = || Error::InvalidLocation(target.display().to_string());
+        let name = target
+            .file_name()
+            .and_then(|n| n.to_str())
+            .ok_or_else(invalid)?;
+        let (hub, port) = name.rsplit_once("-port").ok_or_else(invalid)?;
+        let hub = hub.parse::<Location>()?;
+        match parse_digits(port, 10) {
+            Some(port) if port > 0 => Ok(Some(PortLocation::new(hub, port))),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Check if the device attached to a hub port is authorized
+    pub fn port_authorized(&self, hub: &Location, port: u8) -> Result<bool, Error> {
+        let path = self.port_device_path(hub, port)?.join("authorized");
+        Ok(self.read_attribute(&path)? != "0")
+    }
+
+    /// Deauthorize or reauthorize the device attached to a hub port
+    ///
+    /// Deauthorizing unbinds all drivers from the device and deconfigures it, like unplugging
+    /// the device without cutting power. Reauthorizing configures the device and binds drivers
+    /// again.
+    pub fn set_port_authorized(
+        &self,
+        hub: &Location,
+        port: u8,
+        authorized: bool,
+    ) -> Result<(), Error> {
+        let path = self.port_device_path(hub, port)?.join("authorized");
+        trace!("Set {} port {} authorized {}", hub, port, authorized);
+        self.write_attribute(&path, if authorized { "1" } else { "0" })
+    }
+
+    /// Directory of the device attached to a hub port
+    fn port_device_path(&self, hub: &Location, port: u8) -> Result<PathBuf, Error> {
+        let location = hub.child(port);
+        let path = self.device_path(&location);
+        if path.is_dir() {
+            Ok(path)
+        } else {
+            Err(Error::NoDevice { location })
+        }
+    }
+
+    /// Read the information of the device at a location, without opening the device
+    pub fn device(&self, location: &Location) -> Result<SysfsDevice, Error> {
+        const DESCRIPTOR: &str = "Device";
+        const LENGTH: usize = 18;
+
+        let path = self.device_path(location);
+        let descriptors_path = path.join("descriptors");
+        let descriptors = std::fs::read(&descriptors_path).map_err(|source| Error::Io {
+            path: descriptors_path,
+            source,
+        })?;
+        let invalid = |source| Error::InvalidDescriptor {
+            context: RequestContext {
+                request: "sysfs descriptors",
+                location: location.clone(),
+                port: None,
+            },
+            source,
+        };
+        let data = descriptors
+            .get(..LENGTH)
+            .ok_or(invalid(ParseError::TooShort {
+                descriptor: DESCRIPTOR,
+                required: LENGTH,
+                actual: descriptors.len(),
+            }))?;
+        if data[1] != 0x01 {
+            return Err(invalid(ParseError::InvalidDescriptorType {
+                descriptor: DESCRIPTOR,
+                expected: 0x01,
+                actual: data[1],
+            }));
+        }
+        Ok(SysfsDevice {
+            location: location.clone(),
+            address: self
+                .read_optional_attribute(&path.join("devnum"))?
+                .and_then(|devnum| devnum.parse().ok()),
+            speed: self
+                .read_optional_attribute(&path.join("speed"))?
+                .and_then(|speed| speed_name(&speed)),
+            usb_version: u16::from_le_bytes([data[2], data[3]]),
+            class: data[4],
+            subclass: data[5],
+            protocol: data[6],
+            vendor_id: u16::from_le_bytes([data[8], data[9]]),
+            product_id: u16::from_le_bytes([data[10], data[11]]),
+            device_version: u16::from_le_bytes([data[12], data[13]]),
+            manufacturer: self.read_optional_attribute(&path.join("manufacturer"))?,
+            product: self.read_optional_attribute(&path.join("product"))?,
+            serial_number: self.read_optional_attribute(&path.join("serial"))?,
+        })
+    }
+
+    /// Read the information of all USB devices, ordered by location
+    pub fn devices(&self) -> Result<Vec<SysfsDevice>, Error> {
+        let path = self.root.join("bus/usb/devices");
+        let entries = std::fs::read_dir(&path).map_err(|source| Error::Io {
+            path: path.clone(),
+            source,
+        })?;
+        let mut locations = Vec::new();
+        for entry in entries {
+            let entry = entry.map_err(|source| Error::Io {
+                path: path.clone(),
+                source,
+            })?;
+            // Interfaces are named `<device>:<configuration>.<interface>`
+            if let Some(location) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.parse::<Location>().ok())
+            {
+                locations.push(location);
+            }
+        }
+        locations.sort();
+        locations.iter().map(|l| self.device(l)).collect()
+    }
+
+    /// Read the status of a hub port
+    pub fn port(&self, hub: &Location, port: u8) -> Result<SysfsPort, Error> {
+        let path = self.port_path(hub, port);
+        Ok(SysfsPort {
+            port,
+            state: self.read_optional_attribute(&path.join("state"))?,
+            connect_type: self.read_optional_attribute(&path.join("connect_type"))?,
+            over_current_count: self
+                .read_optional_attribute(&path.join("over_current_count"))?
+                .and_then(|count| count.parse().ok()),
+            disabled: self
+                .read_optional_attribute(&path.join("disable"))?
+                .map(|disable| disable == "1"),
+        })
+    }
+
+    /// Read the information of the hub at a location and all its ports, without opening the hub
+    pub fn hub(&self, location: &Location) -> Result<SysfsHub, Error> {
+        let device = self.device(location)?;
+        if !device.is_hub() {
+            return Err(Error::InvalidDeviceClass {
+                location: location.clone(),
+                class: device.class,
+            });
+        }
+        let port_count = self
+            .read_attribute(&self.device_path(location).join("maxchild"))?
+            .parse::<u8>()
+            .unwrap_or(0);
+        let ports = (1..=port_count)
+            .map(|port| self.port(location, port))
+            .collect::<Result<Vec<SysfsPort>, Error>>()?;
+        Ok(SysfsHub { device, ports })
+    }
+
+    /// Read a sysfs attribute, without trailing white space
+    pub(crate) fn read_attribute(&self, path: &Path) -> Result<String, Error> {
+        std::fs::read_to_string(path)
+            .map(|value| value.trim_end().to_string())
+            .map_err(|source| Error::Io {
+                path: path.to_path_buf(),
+                source,
+            })
+    }
+
+    /// Read a sysfs attribute, `None` if the attribute does not exist
+    pub(crate) fn read_optional_attribute(&self, path: &Path) -> Result<Option<String>, Error> {
+        match std::fs::read_to_string(path) {
+            Ok(value) => Ok(Some(value.trim_end().to_string())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(source) => Err(Error::Io {
+                path: path.to_path_buf(),
+                source,
+            }),
+        }
+    }
+
+    /// Write a sysfs attribute
+    pub(crate) fn write_attribute(&self, path: &Path, value: &str) -> Result<(), Error> {
+        if self.dry_run {
+            info!("Dry run, write {} to {}", value, path.display());
+            return Ok(());
+        }
+        std::fs::write(path, value).map_err(|source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use nusb::{DeviceInfo, MaybeFuture};
+use serde::{Serialize, Serializer};
+
+use crate::Error;
+
+/// USB hub device class
+pub const DEVICE_CLASS_HUB: u8 = 0x09;
+
+/// Location of a USB device, the bus number and the chain of hub ports leading to the device
+///
+/// Formatted and parsed in the Linux style, `1-2.3` for the device on port 3 of the hub on
+/// port 2 of bus 1. The root hub of a bus is formatted as `usb1`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Location {
+    busnum: u8,
+    port_chain: Vec<u8>,
+}
+
+impl Location {
+    /// Create a location from bus number and port chain
+    pub fn new(busnum: u8, port_chain: &[u8]) -> Self {
+        Self {
+            busnum,
+            port_chain: port_chain.to_vec(),
+        }
+    }
+
+    /// Get the location of a device
+    pub fn from_device_info(info: &DeviceInfo) -> Self {
+        Self::new(info.busnum(), info.port_chain())
+    }
+
+    /// Bus number
+    pub fn busnum(&self) -> u8 {
+        self.busnum
+    }
+
+    /// Hub ports leading to the device, empty for the root hub
+    pub fn port_chain(&self) -> &[u8] {
+        &self.port_chain
+    }
+
+    /// Number of hubs between the root hub and the device
+    pub fn depth(&self) -> usize {
+        self.port_chain.len()
+    }
+
+    /// Location of the device attached to `port` of this location
+    pub fn child(&self, port: u8) -> Self {
+        let mut port_chain = self.port_chain.clone();
+        port_chain.push(port);
+        Self {
+            busnum: self.busnum,
+            port_chain,
+        }
+    }
+
+    /// Location of the parent hub and the port of the parent hub, `None` for the root hub
+    pub fn parent(&self) -> Option<(Self, u8)> {
+        let (port, port_chain) = self.port_chain.split_last()?;
+        Some((Self::new(self.busnum, port_chain), *port))
+    }
+
+    /// Check if this location is `other` or is below `other` in the topology
+    pub fn is_within(&self, other: &Location) -> bool {
+        self.busnum == other.busnum && self.port_chain.starts_with(&other.port_chain)
+    }
+}
+
+impl Serialize for Location {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.port_chain.is_empty() {
+            return write!(f, "usb{}", self.busnum);
+        }
+        write!(f, "{}-", self.busnum)?;
+        for (n, port) in self.port_chain.iter().enumerate() {
+            if n > 0 {
+                write!(f, ".")?;
+            }
+            write!(f, "{}", port)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a number consisting of digits in the given radix only
+///
+/// Unlike `from_str_radix` no sign is accepted, `None` for anything else or a value out of
+/// range of `T`.
+pub fn parse_digits<T: TryFrom<u32>>(s: &str, radix: u32) -> Option<T> {
+    if s.is_empty() || !s.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    u32::from_str_radix(s, radix).ok()?.try_into().ok()
+}
+
+impl FromStr for Location {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidLocation(s.to_string());
+        if let Some(busnum) = s.strip_prefix("usb") {
+            let busnum = parse_digits(busnum, 10).ok_or_else(invalid)?;
+            return Ok(Self::new(busnum, &[]));
+        }
+        let (busnum, chain) = s.split_once('-').ok_or_else(invalid)?;
+        let busnum = parse_digits(busnum, 10).ok_or_else(invalid)?;
+        let port_chain = chain
+            .split('.')
+            .map(|v| match parse_digits(v, 10) {
+                Some(port) if port > 0 => Ok(port),
+                _ => Err(invalid()),
+            })
+            .collect::<Result<Vec<u8>, Error>>()?;
+        Ok(Self { busnum, port_chain })
+    }
+}
+
+/// Snapshot of the USB devices in the system, ordered by location
+///
+/// Iterating in location order visits a hub before the devices attached to it.
+pub struct Topology {
+    devices: BTreeMap<Location, DeviceInfo>,
+}
+
+impl Topology {
+    /// Enumerate all USB devices in the system
+    pub fn enumerate() -> Result<Self, Error> {
+        let devices = nusb::list_devices()
+            .wait()
+            .map_err(|e| Error::Enumerate(e.into()))?;
+        Ok(Self::from_devices(devices))
+    }
+
+    /// Create a topology from a set of devices
+    pub fn from_devices<I: IntoIterator<Item = DeviceInfo>>(devices: I) -> Self {
+        let devices = devices
+            .into_iter()
+            .map(|info| (Location::from_device_info(&info), info))
+            .collect();
+        Self { devices }
+    }
+
+    /// Iterate over all devices
+    pub fn devices(&self) -> impl Iterator<Item = (&Location, &DeviceInfo)> {
+        self.devices.iter()
+    }
+
+    /// Iterate over all hubs
+    pub fn hubs(&self) -> impl Iterator<Item = (&Location, &DeviceInfo)> {
+        self.devices
+            .iter()
+            .filter(|(_, info)| info.class() == DEVICE_CLASS_HUB)
+    }
+
+    /// Iterate over the hubs attached directly to a root hub
+    pub fn top_level_hubs(&self) -> impl Iterator<Item = (&Location, &DeviceInfo)> {
+        self.hubs().filter(|(location, _)| location.depth() == 1)
+    }
+
+    /// Get the device at a location
+    pub fn device(&self, location: &Location) -> Option<&DeviceInfo> {
+        self.devices.get(location)
+    }
+
+    /// Check if the device at a location is a hub
+    pub fn is_hub(&self, location: &Location) -> bool {
+        self.device(location)
+            .is_some_and(|info| info.class() == DEVICE_CLASS_HUB)
+    }
+
+    /// Get the device attached to `port` of the hub at `hub`
+    pub fn device_on_port(&self, hub: &Location, port: u8) -> Option<&DeviceInfo> {
+        self.devices.get(&hub.child(port))
+    }
+
+    /// Iterate over the devices attached directly to the hub at `hub`, with their port number
+    pub fn ports(&self, hub: &Location) -> impl Iterator<Item = (u8, &DeviceInfo)> + use<'_> {
+        let depth = hub.depth() + 1;
+        self.devices_under(hub)
+            .filter(move |(location, _)| location.depth() == depth)
+            .map(move |(location, info)| (location.port_chain()[depth - 1], info))
+    }
+
+    /// Get the hub a device is attached to, with the location of the hub and the port number
+    pub fn parent_hub(&self, location: &Location) -> Option<(Location, u8, &DeviceInfo)> {
+        let (parent, port) = location.parent()?;
+        let info = self.devices.get(&parent)?;
+        Some((parent, port, info))
+    }
+
+    /// Iterate over all devices below a location, not including the device at the location
+    pub fn devices_under(
+        &self,
+        location: &Location,
+    ) -> impl Iterator<Item = (&Location, &DeviceInfo)> + use<'_> {
+        let location = location.clone();
+        self.devices
+            .range(location.child(1)..)
+            .take_while(move |(l, _)| l.is_within(&location))
+    }
+}
@@ -0,0 +1,50 @@
+use crate::Location;
+
+/// udev rules granting a group access to the hubs of one model, optionally at one location
+#[derive(Clone, Debug, PartialEq)]
+pub struct UdevRule {
+    /// Hub location, `None` for all hubs of the model
+    pub location: Option<Location>,
+    /// Vendor ID
+    pub vendor_id: u16,
+    /// Product ID
+    pub product_id: u16,
+    /// Manufacturer and product, written as a comment
+    pub description: String,
+}
+
+impl UdevRule {
+    /// Format the rules granting `group` access to the usbfs node with `mode`
+    ///
+    /// With `sysfs` the group also gets write access to the `disable` attributes of the hub
+    /// ports. That rule matches the hub device itself, not its ancestors, so downstream hubs
+    /// are not covered by it.
+    pub fn format(&self, group: &str, mode: &str, sysfs: bool) -> String {
+        let mut rules = String::new();
+        if !self.description.is_empty() {
+            rules.push_str(&format!("# {}\n", self.description));
+        }
+        let kernel = self
+            .location
+            .as_ref()
+            .map(|l| format!("KERNEL==\"{}\", ", l))
+            .unwrap_or_default();
+        let device = format!(
+            "SUBSYSTEM==\"usb\", DEVTYPE==\"usb_device\", {}ATTR{{idVendor}}==\"{:04x}\", ATTR{{idProduct}}==\"{:04x}\"",
+            kernel, self.vendor_id, self.product_id
+        );
+        rules.push_str(&format!(
+            "{}, MODE=\"{}\", GROUP=\"{}\"\n",
+            device, mode, group
+        ));
+        if sysfs {
+            // The ports only exist once the hub driver is bound to the interface, which is
+            // reported by the bind and change events of the device
+            rules.push_str(&format!(
+                "ACTION!=\"remove\", {}, RUN+=\"/bin/sh -c 'chgrp {} $sys$devpath/*:*/*-port*/disable; chmod g+w $sys$devpath/*:*/*-port*/disable'\"\n",
+                device, group
+            ));
+        }
+        rules
+    }
+}
@@ -0,0 +1,82 @@
+use usb_hub_control::{Aliases, Location, PortList, PortLocation, parse_digits};
+
+#[test]
+fn port_location() {
+    let port = "1-2.3:4".parse::<PortLocation>().unwrap();
+    assert_eq!(port.hub, Location::new(1, &[2, 3]));
+    assert_eq!(port.port, 4);
+    assert_eq!(port.device(), Location::new(1, &[2, 3, 4]));
+    assert_eq!(port.to_string(), "1-2.3:4");
+    assert_eq!(
+        "usb2:1".parse::<PortLocation>().unwrap().hub,
+        Location::new(2, &[])
+    );
+
+    assert!("1-2.3".parse::<PortLocation>().is_err());
+    assert!("1-2.3:0".parse::<PortLocation>().is_err());
+    assert!("1-2.3:a".parse::<PortLocation>().is_err());
+    assert!("1-2.3:+4".parse::<PortLocation>().is_err());
+}
+
+#[test]
+fn port_list() {
+    assert_eq!("all".parse::<PortList>().unwrap(), PortList::All);
+    assert_eq!(PortList::All.ports(3), vec![1, 2, 3]);
+    assert_eq!(
+        "4,1-3,2".parse::<PortList>().unwrap(),
+        PortList::Ports(vec![1, 2, 3, 4])
+    );
+
+    for invalid in [
+        "", "0", "3-1", "1,", "+3", " 3", "3 ", "1-+3", "1-", "256", "a",
+    ] {
+        assert!(invalid.parse::<PortList>().is_err(), "{:?}", invalid);
+    }
+}
+
+#[test]
+fn digits_only() {
+    assert_eq!(parse_digits::<u8>("255", 10), Some(255));
+    assert_eq!(parse_digits::<u8>("256", 10), None);
+    assert_eq!(parse_digits::<u16>("05e3", 16), Some(0x05e3));
+    assert_eq!(parse_digits::<u16>("+5e3", 16), None);
+    assert_eq!(parse_digits::<u8>("-1", 10), None);
+    assert_eq!(parse_digits::<u8>("", 10), None);
+}
+
+#[test]
+fn parse_toml() {
+    let aliases = Aliases::from_toml(
+        r#"
+dut-rpi4 = "1-2.3:4"
+dut-usb3 = { port = "2-1:1", companion = "1-1" }
+"#,
+    )
+    .unwrap();
+
+    let alias = aliases.get("dut-rpi4").unwrap();
+    assert_eq!(alias.port.to_string(), "1-2.3:4");
+    assert!(alias.companion.is_none());
+
+    let alias = aliases.get("dut-usb3").unwrap();
+    assert_eq!(
+        alias.hubs().cloned().collect::<Vec<Location>>(),
+        vec![Location::new(2, &[1]), Location::new(1, &[1])]
+    );
+    assert_eq!(aliases.name_of(&"2-1:1".parse().unwrap()), Some("dut-usb3"));
+}
+
+#[test]
+fn resolve() {
+    let aliases = Aliases::from_toml("dut = \"1-2.3:4\"\n").unwrap();
+    assert_eq!(aliases.resolve("dut").unwrap().port.to_string(), "1-2.3:4");
+    assert_eq!(aliases.resolve("3-1:2").unwrap().port.to_string(), "3-1:2");
+    assert!(aliases.resolve("unknown").is_err());
+}
+
+#[test]
+fn invalid_toml() {
+    assert!(Aliases::from_toml("dut = \"1-2.3\"\n").is_err());
+    assert!(Aliases::from_toml("dut = { port = \"1-2:1\", companion = \"x\" }\n").is_err());
+    assert!(Aliases::from_toml("dut = 4\n").is_err());
+}
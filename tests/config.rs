@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+use usb_hub_control::{Config, Location, PortLocation};
+
+const CONFIG: &str = r#"
+timeout = 1000
+hub = "1-2"
+protected = ["1-2:1", "dut"]
+
+[aliases]
+dut = { port = "2-1:3", companion = "1-1" }
+
+[[quirk]]
+vendor_id = 0x05e3
+product_id = 0x0610
+ganged = true
+"#;
+
+#[test]
+fn parse_toml() {
+    let config = Config::from_toml(CONFIG).unwrap();
+    assert_eq!(config.timeout, Some(Duration::from_millis(1000)));
+    assert_eq!(config.settle, None);
+    assert_eq!(config.hub, Some(Location::new(1, &[2])));
+    assert!(config.aliases.get("dut").is_some());
+    assert!(config.quirks.lookup(0x05e3, 0x0610, 0x0100).unwrap().ganged);
+}
+
+#[test]
+fn protected_ports() {
+    let config = Config::from_toml(CONFIG).unwrap();
+    let protected = config
+        .protected_ports()
+        .unwrap()
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<String>>();
+    assert_eq!(protected, vec!["1-2:1", "2-1:3", "1-1:3"]);
+
+    assert!(config.is_protected(&PortLocation::new(Location::new(1, &[1]), 3)));
+    assert!(!config.is_protected(&PortLocation::new(Location::new(1, &[1]), 2)));
+}
+
+#[test]
+fn merge() {
+    let mut config = Config::from_toml(CONFIG).unwrap();
+    config.merge(Config::from_toml("settle = 500\nhub = \"3-1\"\n").unwrap());
+    assert_eq!(config.timeout, Some(Duration::from_millis(1000)));
+    assert_eq!(config.settle, Some(Duration::from_millis(500)));
+    assert_eq!(config.hub, Some(Location::new(3, &[1])));
+    assert!(config.aliases.get("dut").is_some());
+}
+
+#[test]
+fn invalid_toml() {
+    assert!(Config::from_toml("unknown = 1\n").is_err());
+    assert!(Config::from_toml("hub = \"1-x\"\n").is_err());
+    assert!(Config::from_toml("[aliases]\ndut = \"1-2\"\n").is_err());
+}
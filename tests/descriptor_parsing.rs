@@ -0,0 +1,297 @@
+use std::time::Duration;
+
+use nusb::transfer::{Control, ControlType, Recipient};
+use proptest::prelude::*;
+
+use usb_hub_control::{
+    BinaryObjectStoreDescriptor, ContainerId, DeviceCapability, HubDescriptor,
+    OverCurrentProtectionMode, ParseError, PortChange, SetupPacket,
+};
+
+const CONTAINER_ID: [u8; 16] = [
+    0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff,
+];
+
+fn bos(capabilities: &[&[u8]]) -> Vec<u8> {
+    let total = 5 + capabilities.iter().map(|c| c.len()).sum::<usize>();
+    let mut data = vec![5, 0x0f];
+    data.extend((total as u16).to_le_bytes());
+    data.push(capabilities.len() as u8);
+    for capability in capabilities {
+        data.extend(*capability);
+    }
+    data
+}
+
+fn container_id_capability() -> Vec<u8> {
+    let mut data = vec![20, 0x10, 0x04, 0x00];
+    data.extend(CONTAINER_ID);
+    data
+}
+
+#[test]
+fn bos_container_id() {
+    let usb2 = [7, 0x10, 0x02, 0x06, 0x00, 0x00, 0x00];
+    let bos =
+        BinaryObjectStoreDescriptor::from_data(&bos(&[&usb2, &container_id_capability()])).unwrap();
+    assert_eq!(bos.capabilities().count(), 2);
+    assert_eq!(bos.container_id(), Some(ContainerId(CONTAINER_ID)));
+}
+
+#[test]
+fn bos_capabilities() {
+    let usb2 = [7, 0x10, 0x02, 0x1e, 0x34, 0x00, 0x00];
+    let super_speed = [10, 0x10, 0x03, 0x00, 0x0e, 0x00, 0x01, 0x0a, 0xff, 0x07];
+    let super_speed_plus = [
+        20, 0x10, 0x0a, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x11, 0x00, 0x00, 0x30, 0x40, 0x0a,
+        0x00, 0xb0, 0x40, 0x0a, 0x00,
+    ];
+    let ptm = [3, 0x10, 0x0b];
+    let unknown = [4, 0x10, 0x42, 0x01];
+    let data = bos(&[&usb2, &super_speed, &super_speed_plus, &ptm, &unknown]);
+    let bos = BinaryObjectStoreDescriptor::from_data(&data).unwrap();
+    let capabilities = bos.capabilities().collect::<Vec<_>>();
+    assert_eq!(capabilities.len(), 5);
+
+    let DeviceCapability::Usb20Extension(usb2) = capabilities[0] else {
+        panic!("Expected USB 2.0 extension");
+    };
+    assert!(usb2.lpm());
+    assert!(usb2.besl());
+    assert_eq!(usb2.baseline_besl(), Some(4));
+    assert_eq!(usb2.deep_besl(), Some(3));
+
+    let DeviceCapability::SuperSpeedUsb(super_speed) = capabilities[1] else {
+        panic!("Expected SuperSpeed USB");
+    };
+    assert!(!super_speed.low_speed());
+    assert!(super_speed.full_speed());
+    assert!(super_speed.high_speed());
+    assert!(super_speed.super_speed());
+    assert_eq!(super_speed.u1_exit_latency(), 0x0a);
+    assert_eq!(super_speed.u2_exit_latency(), 0x07ff);
+
+    let DeviceCapability::SuperSpeedPlus(super_speed_plus) = capabilities[2] else {
+        panic!("Expected SuperSpeedPlus");
+    };
+    let attributes = super_speed_plus.sublink_speed_attributes();
+    assert_eq!(attributes.len(), 2);
+    assert_eq!(attributes[0].lane_speed(), 10_000_000_000);
+    assert!(!attributes[0].transmit());
+    assert!(attributes[1].transmit());
+    assert_eq!(super_speed_plus.min_rx_lanes(), 1);
+    assert_eq!(super_speed_plus.min_tx_lanes(), 1);
+
+    assert_eq!(capabilities[3], &DeviceCapability::PrecisionTimeMeasurement);
+    assert_eq!(
+        capabilities[4],
+        &DeviceCapability::Unknown {
+            capability_type: 0x42,
+            data: unknown.to_vec()
+        }
+    );
+}
+
+#[test]
+fn bos_larger_than_256_bytes() {
+    let platform = {
+        let mut data = vec![200, 0x10, 0x05, 0x00];
+        data.extend([0u8; 196]);
+        data
+    };
+    let data = bos(&[&platform, &platform, &container_id_capability()]);
+    let bos = BinaryObjectStoreDescriptor::from_data(&data).unwrap();
+    assert_eq!(bos.container_id(), Some(ContainerId(CONTAINER_ID)));
+}
+
+#[test]
+fn bos_truncated() {
+    let data = bos(&[&container_id_capability()]);
+    assert_eq!(
+        BinaryObjectStoreDescriptor::from_data(&data[..data.len() - 1]),
+        Err(ParseError::TooShort {
+            descriptor: "BOS",
+            required: data.len(),
+            actual: data.len() - 1,
+        })
+    );
+}
+
+#[test]
+fn bos_missing_capabilities() {
+    // The capabilities found are kept when fewer than declared follow the header
+    let mut data = bos(&[&container_id_capability()]);
+    data[4] = 2;
+    let descriptor = BinaryObjectStoreDescriptor::from_data(&data).unwrap();
+    assert_eq!(descriptor.capabilities().count(), 1);
+    assert_eq!(descriptor.container_id(), Some(ContainerId(CONTAINER_ID)));
+}
+
+#[test]
+fn bos_short_capability() {
+    let short = [5, 0x10, 0x04, 0x00, 0x00];
+    assert!(matches!(
+        DeviceCapability::from_data(&short),
+        Err(ParseError::TooShort {
+            descriptor: "Container ID",
+            ..
+        })
+    ));
+    let data = bos(&[&short]);
+    let bos = BinaryObjectStoreDescriptor::from_data(&data).unwrap();
+    assert_eq!(
+        bos.capabilities().collect::<Vec<_>>(),
+        vec![&DeviceCapability::Unknown {
+            capability_type: 0x04,
+            data: short.to_vec()
+        }]
+    );
+}
+
+#[test]
+fn bos_malformed_capability_keeps_others() {
+    let short_super_speed = [4, 0x10, 0x03, 0x00];
+    let data = bos(&[&short_super_speed, &container_id_capability()]);
+    let descriptor = BinaryObjectStoreDescriptor::from_data(&data).unwrap();
+    assert_eq!(descriptor.capabilities().count(), 2);
+    assert_eq!(descriptor.container_id(), Some(ContainerId(CONTAINER_ID)));
+
+    let invalid_header = [2, 0x10, 0x03];
+    let data = bos(&[&container_id_capability(), &invalid_header]);
+    let descriptor = BinaryObjectStoreDescriptor::from_data(&data).unwrap();
+    assert_eq!(descriptor.capabilities().count(), 1);
+    assert_eq!(descriptor.container_id(), Some(ContainerId(CONTAINER_ID)));
+}
+
+#[test]
+fn hub_descriptor() {
+    let data = [9, 0x29, 4, 0x09, 0x00, 0x32, 0x64, 0x00, 0xff];
+    let hub = HubDescriptor::from_data(&data).unwrap();
+    assert_eq!(hub.port_count(), 4);
+    assert!(HubDescriptor::from_data(&data[..6]).is_err());
+    assert_eq!(
+        hub.over_current_protection_mode(),
+        OverCurrentProtectionMode::IndividualPort
+    );
+    assert_eq!(hub.tt_think_time(), Some(8));
+    assert!(!hub.port_indicators());
+    assert_eq!(hub.power_good_time(), Duration::from_millis(100));
+    assert_eq!(hub.controller_current(), 100);
+    assert_eq!(hub.port_removable(4), Some(true));
+    assert_eq!(hub.port_removable(5), None);
+    assert_eq!(
+        HubDescriptor::from_data(&data[..7])
+            .unwrap()
+            .port_removable(1),
+        None
+    );
+}
+
+#[test]
+fn superspeed_hub_descriptor() {
+    let data = [
+        12, 0x2a, 4, 0xa9, 0x00, 0x32, 0x19, 0x00, 0x00, 0x00, 0x04, 0x00,
+    ];
+    let hub = HubDescriptor::from_data(&data).unwrap();
+    assert!(hub.super_speed());
+    assert!(!hub.compound_device());
+    assert_eq!(hub.tt_think_time(), None);
+    assert!(!hub.port_indicators());
+    assert_eq!(hub.controller_current(), 100);
+    assert_eq!(hub.port_removable(1), Some(true));
+    assert_eq!(hub.port_removable(2), Some(false));
+}
+
+#[test]
+fn port_change() {
+    assert_eq!(
+        PortChange::from_field(0x0019, false).names(),
+        vec!["connection", "overcurrent", "reset"]
+    );
+    assert_eq!(
+        PortChange::from_field(0x0062, true).names(),
+        vec!["bh-reset", "link-state"]
+    );
+}
+
+proptest! {
+    #[test]
+    fn bos_random_data(data in proptest::collection::vec(any::<u8>(), 0..600)) {
+        let _ = BinaryObjectStoreDescriptor::from_data(&data);
+    }
+
+    #[test]
+    fn bos_random_capabilities(
+        count in any::<u8>(),
+        capabilities in proptest::collection::vec(any::<u8>(), 0..600),
+    ) {
+        let total = (5 + capabilities.len()) as u16;
+        let mut data = vec![5, 0x0f];
+        data.extend(total.to_le_bytes());
+        data.push(count);
+        data.extend(capabilities);
+        let _ = BinaryObjectStoreDescriptor::from_data(&data);
+    }
+
+    #[test]
+    fn capability_random_data(
+        length in any::<u8>(),
+        capability_type in any::<u8>(),
+        data in proptest::collection::vec(any::<u8>(), 0..300),
+    ) {
+        let mut capability = vec![length, 0x10, capability_type];
+        capability.extend(data);
+        let _ = DeviceCapability::from_data(&capability);
+    }
+
+    #[test]
+    fn hub_descriptor_random_data(data in proptest::collection::vec(any::<u8>(), 0..16)) {
+        let _ = HubDescriptor::from_data(&data);
+    }
+}
+
+#[test]
+fn container_id_format() {
+    let container_id = ContainerId(CONTAINER_ID);
+    let canonical = "33221100-5544-7766-8899-aabbccddeeff";
+    assert_eq!(container_id.to_string(), canonical);
+    assert_eq!(canonical.parse::<ContainerId>().unwrap(), container_id);
+    assert_eq!(
+        "{33221100-5544-7766-8899-AABBCCDDEEFF}"
+            .parse::<ContainerId>()
+            .unwrap(),
+        container_id
+    );
+    assert!(
+        "33221100-5544-7766-8899-aabbccddeef"
+            .parse::<ContainerId>()
+            .is_err()
+    );
+    assert!(
+        "33221100554477668899aabbccddeeff"
+            .parse::<ContainerId>()
+            .is_err()
+    );
+    assert!(
+        "+3221100-5544-7766-8899-aabbccddeeff"
+            .parse::<ContainerId>()
+            .is_err()
+    );
+}
+
+#[test]
+fn setup_packet() {
+    let control = Control {
+        control_type: ControlType::Class,
+        recipient: Recipient::Other,
+        request: 0x01,
+        value: 0x0008,
+        index: 3,
+    };
+    let setup = SetupPacket::control_out(&control, &[]);
+    assert_eq!(setup.request_type, 0x23);
+    assert_eq!(
+        setup.to_string(),
+        "bmRequestType 0x23 bRequest 0x01 wValue 0x0008 wIndex 0x0003 wLength 0"
+    );
+}
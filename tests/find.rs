@@ -0,0 +1,74 @@
+use usb_hub_control::{DeviceSelector, DeviceSummary, FoundDevice, Location, PortLocation};
+
+fn device(location: Location) -> DeviceSummary {
+    DeviceSummary {
+        location,
+        busnum: 1,
+        address: Some(7),
+        vendor_id: 0x0483,
+        product_id: 0x374b,
+        class: 0xef,
+        subclass: 0x02,
+        protocol: 0x01,
+        device_version: 0x0100,
+        speed: Some("full"),
+        manufacturer: Some(String::from("STMicroelectronics")),
+        product: Some(String::from("STM32 STLink")),
+        serial_number: Some(String::from("ABC123")),
+    }
+}
+
+#[test]
+fn selector() {
+    let device = device(Location::new(1, &[2, 3]));
+    assert!(DeviceSelector::default().is_empty());
+    assert!(DeviceSelector::default().matches(&device));
+
+    let selector = DeviceSelector {
+        vendor_id: Some(0x0483),
+        product_id: Some(0x374b),
+        serial_number: Some(String::from("ABC123")),
+        ..Default::default()
+    };
+    assert!(!selector.is_empty());
+    assert!(selector.matches(&device));
+
+    let selector = DeviceSelector {
+        bus_address: Some((1, 7)),
+        product: Some(String::from("STM32 STLink")),
+        ..Default::default()
+    };
+    assert!(selector.matches(&device));
+
+    let selector = DeviceSelector {
+        serial_number: Some(String::from("ABC124")),
+        ..Default::default()
+    };
+    assert!(!selector.matches(&device));
+    let selector = DeviceSelector {
+        bus_address: Some((2, 7)),
+        ..Default::default()
+    };
+    assert!(!selector.matches(&device));
+}
+
+#[test]
+fn path() {
+    let found = FoundDevice::new(device(Location::new(1, &[2, 3])));
+    assert_eq!(
+        found.port,
+        Some(PortLocation::new(Location::new(1, &[2]), 3))
+    );
+    assert_eq!(
+        found.path,
+        vec![
+            PortLocation::new(Location::new(1, &[]), 2),
+            PortLocation::new(Location::new(1, &[2]), 3),
+        ]
+    );
+    assert_eq!(found.path[0].to_string(), "usb1:2");
+
+    let root = FoundDevice::new(device(Location::new(1, &[])));
+    assert_eq!(root.port, None);
+    assert!(root.path.is_empty());
+}
@@ -0,0 +1,85 @@
+use usb_hub_control::{Event, HubSnapshot, Location, PortLocation, PortStatus, Snapshot};
+
+fn hub(ports: &[(u8, u16)]) -> HubSnapshot {
+    HubSnapshot {
+        vendor_id: 0x2109,
+        product_id: 0x2817,
+        ports: ports
+            .iter()
+            .map(|(port, status)| (*port, PortStatus::from_field(*status, false)))
+            .collect(),
+    }
+}
+
+#[test]
+fn port_changes() {
+    let location = Location::new(1, &[2]);
+    let port = |port| PortLocation::new(location.clone(), port);
+    let mut earlier = Snapshot::default();
+    earlier.insert(
+        location.clone(),
+        hub(&[(1, 0x0100), (2, 0x0103), (3, 0x0100)]),
+    );
+    let mut later = Snapshot::default();
+    later.insert(
+        location.clone(),
+        hub(&[(1, 0x0103), (2, 0x0000), (4, 0x0108)]),
+    );
+
+    assert_eq!(
+        earlier.changes(&later),
+        vec![
+            Event::Connected { port: port(1) },
+            Event::PoweredOff { port: port(2) },
+            Event::Disconnected { port: port(2) },
+        ]
+    );
+    assert!(later.changes(&later).is_empty());
+}
+
+#[test]
+fn over_current() {
+    let location = Location::new(1, &[2]);
+    let mut earlier = Snapshot::default();
+    earlier.insert(location.clone(), hub(&[(1, 0x0100)]));
+    let mut later = Snapshot::default();
+    later.insert(location.clone(), hub(&[(1, 0x0108)]));
+
+    let port = PortLocation::new(location, 1);
+    assert_eq!(
+        earlier.changes(&later),
+        vec![Event::OverCurrent { port: port.clone() }]
+    );
+    assert_eq!(
+        later.changes(&earlier),
+        vec![Event::OverCurrentCleared { port }]
+    );
+}
+
+#[test]
+fn hub_changes() {
+    let mut earlier = Snapshot::default();
+    earlier.insert(Location::new(1, &[1]), hub(&[]));
+    let mut later = Snapshot::default();
+    later.insert(Location::new(1, &[2]), hub(&[]));
+
+    let events = earlier.changes(&later);
+    assert_eq!(
+        events,
+        vec![
+            Event::HubRemoved {
+                hub: Location::new(1, &[1])
+            },
+            Event::HubAdded {
+                hub: Location::new(1, &[2]),
+                vendor_id: 0x2109,
+                product_id: 0x2817,
+            },
+        ]
+    );
+    assert_eq!(events[1].to_string(), "hub 1-2 added 2109:2817");
+    assert_eq!(
+        serde_json::to_value(&events[0]).unwrap(),
+        serde_json::json!({ "event": "hub_removed", "hub": "1-1" })
+    );
+}
@@ -0,0 +1,97 @@
+use usb_hub_control::Quirks;
+
+#[test]
+fn builtin() {
+    let quirks = Quirks::builtin();
+    let quirk = quirks.lookup(0x2109, 0x3431, 0x0420).unwrap();
+    assert!(quirk.ganged);
+    assert!(quirk.companion_required);
+    assert!(quirks.lookup(0x1234, 0x5678, 0x0100).is_none());
+    // Standalone VL817 hubs switch their ports individually
+    assert!(quirks.lookup(0x2109, 0x0817, 0x0100).is_none());
+}
+
+#[test]
+fn parse_toml() {
+    let quirks = Quirks::from_toml(
+        r#"
+[[quirk]]
+vendor_id = 0x05e3
+product_id = 0x0610
+device_version_min = 0x0600
+device_version_max = 0x0699
+no_vbus_ports = [2, 3]
+port_labels = ["A", "B", "C", "D"]
+broken_bos = true
+note = "Genesys Logic GL3520"
+"#,
+    )
+    .unwrap();
+
+    assert!(quirks.lookup(0x05e3, 0x0610, 0x05ff).is_none());
+    assert!(quirks.lookup(0x05e3, 0x0610, 0x0700).is_none());
+    let quirk = quirks.lookup(0x05e3, 0x0610, 0x0654).unwrap();
+    assert!(!quirk.ganged);
+    assert!(quirk.no_vbus(2));
+    assert!(!quirk.no_vbus(1));
+    assert_eq!(quirk.port_label(1), Some("A"));
+    assert_eq!(quirk.port_label(0), None);
+    assert_eq!(quirk.port_label(5), None);
+    assert_eq!(quirk.names(), vec!["no-vbus", "broken-bos"]);
+}
+
+#[test]
+fn local_overrides_builtin() {
+    let mut quirks = Quirks::builtin();
+    quirks.extend(
+        Quirks::from_toml(
+            // Table name used by earlier quirks files
+            r#"
+[[hub]]
+vendor_id = 0x0424
+product_id = 0x9514
+port_labels = ["Ethernet", "USB top", "USB bottom"]
+"#,
+        )
+        .unwrap(),
+    );
+    let quirk = quirks.lookup(0x0424, 0x9514, 0x0200).unwrap();
+    // The local quirk extends the built-in quirk instead of replacing it
+    assert!(quirk.ganged);
+    assert_eq!(quirk.port_label(2), Some("USB top"));
+    assert_eq!(
+        quirk.note.as_deref(),
+        Some("SMSC LAN9514 in Raspberry Pi B+, 2B and 3B")
+    );
+
+    // A probe result written to an empty local file keeps the built-in flags
+    let mut local = Quirks::default();
+    local.entry(0x2109, 0x3431).no_vbus_ports.push(2);
+    quirks.extend(local);
+    let quirk = quirks.lookup(0x2109, 0x3431, 0x0420).unwrap();
+    assert!(quirk.ganged);
+    assert!(quirk.companion_required);
+    assert!(quirk.no_vbus(2));
+}
+
+#[test]
+fn invalid_toml() {
+    assert!(Quirks::from_toml("[[hub]]\nvendor_id = 0x0424\n").is_err());
+    assert!(Quirks::from_toml("[[hub]]\nvendor_id = 0x10000\nproduct_id = 1\n").is_err());
+}
+
+#[test]
+fn entry_round_trip() {
+    let mut quirks = Quirks::default();
+    quirks.entry(0x05e3, 0x0610).no_vbus_ports.push(3);
+    quirks.entry(0x05e3, 0x0610).ganged = true;
+    assert_eq!(quirks.iter().count(), 1);
+
+    let toml = quirks.to_toml();
+    assert!(toml.contains("[[quirk]]"));
+    let parsed = Quirks::from_toml(&toml).unwrap();
+    assert_eq!(parsed, quirks);
+    let quirk = parsed.lookup(0x05e3, 0x0610, 0x0100).unwrap();
+    assert!(quirk.ganged);
+    assert!(quirk.no_vbus(3));
+}
@@ -0,0 +1,59 @@
+use serde_json::json;
+use usb_hub_control::{ContainerId, Location, LogicalPowerSwitchingMode, PortLocation, PortStatus};
+
+#[test]
+fn port_status() {
+    let status = PortStatus::from_field(0x0103, false);
+    assert_eq!(
+        serde_json::to_value(status).unwrap(),
+        json!({
+            "raw": 0x0103,
+            "connection": true,
+            "enabled": true,
+            "suspended": false,
+            "overcurrent": false,
+            "reset": false,
+            "powered": true,
+        })
+    );
+    let status = PortStatus::from_field(0x0203, true);
+    assert_eq!(status.field(), 0x0203);
+    let value = serde_json::to_value(status).unwrap();
+    assert_eq!(value["raw"], 0x0203);
+    assert_eq!(value["powered"], true);
+}
+
+#[test]
+fn locations() {
+    assert_eq!(
+        serde_json::to_value(Location::new(1, &[2, 3])).unwrap(),
+        json!("1-2.3")
+    );
+    assert_eq!(
+        serde_json::to_value(Location::new(2, &[])).unwrap(),
+        json!("usb2")
+    );
+    assert_eq!(
+        serde_json::to_value(PortLocation::new(Location::new(1, &[2]), 4)).unwrap(),
+        json!("1-2:4")
+    );
+}
+
+#[test]
+fn container_id() {
+    let container_id = "{5a1f2c3d-4b5e-6f70-8192-a3b4c5d6e7f8}"
+        .parse::<ContainerId>()
+        .unwrap();
+    assert_eq!(
+        serde_json::to_value(container_id).unwrap(),
+        json!("5a1f2c3d-4b5e-6f70-8192-a3b4c5d6e7f8")
+    );
+}
+
+#[test]
+fn switching_mode() {
+    assert_eq!(
+        serde_json::to_value(LogicalPowerSwitchingMode::IndividualPort).unwrap(),
+        json!("individual_port")
+    );
+}
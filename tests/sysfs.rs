@@ -0,0 +1,237 @@
+use std::fs;
+use std::path::Path;
+
+use usb_hub_control::{
+    Error, HubState, Location, LogicalPowerSwitchingMode, PortControlBackend, PortLocation, Quirk,
+    Quirks, Sysfs,
+};
+
+fn create_port(root: &Path, interface: &str, port: &str, disable: &str) {
+    let path = root.join("bus/usb/devices").join(interface).join(port);
+    fs::create_dir_all(&path).unwrap();
+    fs::write(path.join("disable"), disable).unwrap();
+}
+
+fn read_disable(root: &Path, interface: &str, port: &str) -> String {
+    fs::read_to_string(
+        root.join("bus/usb/devices")
+            .join(interface)
+            .join(port)
+            .join("disable"),
+    )
+    .unwrap()
+}
+
+#[test]
+fn port_disable() {
+    let root = tempfile::tempdir().unwrap();
+    create_port(root.path(), "1-2:1.0", "1-2-port3", "0\n");
+    let sysfs = Sysfs::new(root.path());
+    let hub = "1-2".parse::<Location>().unwrap();
+
+    assert!(!sysfs.port_disabled(&hub, 3).unwrap());
+    sysfs.set_port_disabled(&hub, 3, true).unwrap();
+    assert_eq!(read_disable(root.path(), "1-2:1.0", "1-2-port3"), "1");
+    assert!(sysfs.port_disabled(&hub, 3).unwrap());
+    sysfs.set_port_disabled(&hub, 3, false).unwrap();
+    assert_eq!(read_disable(root.path(), "1-2:1.0", "1-2-port3"), "0");
+}
+
+#[test]
+fn root_hub_port_disable() {
+    let root = tempfile::tempdir().unwrap();
+    create_port(root.path(), "3-0:1.0", "usb3-port1", "0\n");
+    let sysfs = Sysfs::new(root.path());
+    let hub = Location::new(3, &[]);
+
+    sysfs.set_port_disabled(&hub, 1, true).unwrap();
+    assert_eq!(read_disable(root.path(), "3-0:1.0", "usb3-port1"), "1");
+}
+
+#[test]
+fn dry_run_port_disable() {
+    let root = tempfile::tempdir().unwrap();
+    create_port(root.path(), "1-2:1.0", "1-2-port3", "0\n");
+    let mut sysfs = Sysfs::new(root.path());
+    sysfs.set_dry_run(true);
+    let hub = "1-2".parse::<Location>().unwrap();
+
+    sysfs.set_port_disabled(&hub, 3, true).unwrap();
+    assert_eq!(read_disable(root.path(), "1-2:1.0", "1-2-port3"), "0\n");
+}
+
+#[test]
+fn no_vbus_port_round_trip() {
+    let root = tempfile::tempdir().unwrap();
+    create_port(root.path(), "1-2:1.0", "1-2-port3", "0\n");
+    let sysfs = Sysfs::new(root.path());
+    let hub = "1-2".parse::<Location>().unwrap();
+    let quirk = Quirk {
+        no_vbus_ports: vec![3],
+        ..Default::default()
+    };
+    let mode = LogicalPowerSwitchingMode::IndividualPort;
+
+    // Switching off and on both go through sysfs, so the port does not stay disabled
+    let backend = PortControlBackend::Auto.resolve(mode, Some(&quirk), 3);
+    assert_eq!(backend, PortControlBackend::Sysfs);
+    sysfs.set_port_disabled(&hub, 3, true).unwrap();
+    assert!(sysfs.port_disabled(&hub, 3).unwrap());
+    sysfs.set_port_disabled(&hub, 3, false).unwrap();
+    assert_eq!(read_disable(root.path(), "1-2:1.0", "1-2-port3"), "0");
+
+    assert_eq!(
+        PortControlBackend::Auto.resolve(mode, Some(&quirk), 2),
+        PortControlBackend::Usb
+    );
+    assert_eq!(
+        PortControlBackend::Usb.resolve(mode, Some(&quirk), 3),
+        PortControlBackend::Usb
+    );
+    assert_eq!(
+        PortControlBackend::Auto.resolve(LogicalPowerSwitchingMode::Common, None, 2),
+        PortControlBackend::Sysfs
+    );
+}
+
+#[test]
+fn port_peer() {
+    let root = tempfile::tempdir().unwrap();
+    create_port(root.path(), "1-1:1.0", "1-1-port2", "0\n");
+    create_port(root.path(), "1-1:1.0", "1-1-port3", "0\n");
+    create_port(root.path(), "2-0:1.0", "usb2-port1", "0\n");
+    std::os::unix::fs::symlink(
+        "../../../2-0:1.0/usb2-port1",
+        root.path().join("bus/usb/devices/1-1:1.0/1-1-port2/peer"),
+    )
+    .unwrap();
+    let sysfs = Sysfs::new(root.path());
+    let hub = "1-1".parse::<Location>().unwrap();
+
+    assert_eq!(
+        sysfs.port_peer(&hub, 2).unwrap(),
+        Some(PortLocation::new(Location::new(2, &[]), 1))
+    );
+    assert_eq!(sysfs.port_peer(&hub, 3).unwrap(), None);
+}
+
+#[test]
+fn missing_port() {
+    let root = tempfile::tempdir().unwrap();
+    create_port(root.path(), "1-2:1.0", "1-2-port3", "0\n");
+    let sysfs = Sysfs::new(root.path());
+    let hub = "1-2".parse::<Location>().unwrap();
+
+    assert!(matches!(
+        sysfs.port_disabled(&hub, 4),
+        Err(Error::Io { path, .. }) if path.ends_with("1-2:1.0/1-2-port4/disable")
+    ));
+}
+
+fn create_device(root: &Path, name: &str, class: u8, vendor_id: u16, product_id: u16) {
+    let path = root.join("bus/usb/devices").join(name);
+    fs::create_dir_all(&path).unwrap();
+    let mut descriptors = vec![18, 0x01, 0x10, 0x02, class, 0x00, 0x01, 0x40];
+    descriptors.extend(vendor_id.to_le_bytes());
+    descriptors.extend(product_id.to_le_bytes());
+    descriptors.extend([0x00, 0x01, 0x01, 0x02, 0x03, 0x01]);
+    // Configuration descriptor following the device descriptor
+    descriptors.extend([9, 0x02, 0x19, 0x00, 0x01, 0x01, 0x00, 0xe0, 0x00]);
+    fs::write(path.join("descriptors"), descriptors).unwrap();
+}
+
+fn create_port_attributes(root: &Path, interface: &str, port: &str, state: &str) {
+    create_port(root, interface, port, "0\n");
+    let path = root.join("bus/usb/devices").join(interface).join(port);
+    fs::write(path.join("state"), format!("{}\n", state)).unwrap();
+    fs::write(path.join("connect_type"), "hotplug\n").unwrap();
+    fs::write(path.join("over_current_count"), "0\n").unwrap();
+}
+
+#[test]
+fn read_only_hub() {
+    let root = tempfile::tempdir().unwrap();
+    create_device(root.path(), "1-2", 0x09, 0x05e3, 0x0610);
+    fs::write(root.path().join("bus/usb/devices/1-2/maxchild"), "2\n").unwrap();
+    fs::write(
+        root.path().join("bus/usb/devices/1-2/product"),
+        "USB2.1 Hub\n",
+    )
+    .unwrap();
+    create_port_attributes(root.path(), "1-2:1.0", "1-2-port1", "configured");
+    create_port_attributes(root.path(), "1-2:1.0", "1-2-port2", "not attached");
+    create_device(root.path(), "1-2.1", 0x00, 0x0483, 0x374b);
+    fs::create_dir_all(root.path().join("bus/usb/devices/1-2.1:1.0")).unwrap();
+
+    let sysfs = Sysfs::new(root.path());
+    let hub = sysfs.hub(&"1-2".parse::<Location>().unwrap()).unwrap();
+    assert_eq!(hub.device.vendor_id, 0x05e3);
+    assert_eq!(hub.device.product_id, 0x0610);
+    assert_eq!(hub.device.product.as_deref(), Some("USB2.1 Hub"));
+    assert_eq!(hub.device.serial_number, None);
+    assert_eq!(hub.port_count(), 2);
+    assert!(hub.ports[0].connected());
+    assert!(!hub.ports[1].connected());
+    assert_eq!(hub.ports[0].connect_type.as_deref(), Some("hotplug"));
+    assert_eq!(hub.ports[0].over_current_count, Some(0));
+    assert_eq!(hub.ports[0].disabled, Some(false));
+
+    let devices = sysfs.devices().unwrap();
+    assert_eq!(devices.len(), 2);
+    assert!(devices[0].is_hub());
+    assert_eq!(devices[1].location, "1-2.1".parse::<Location>().unwrap());
+    assert!(matches!(
+        sysfs.hub(&devices[1].location),
+        Err(Error::InvalidDeviceClass { class: 0x00, .. })
+    ));
+}
+
+#[test]
+fn read_only_hub_state() {
+    let root = tempfile::tempdir().unwrap();
+    create_device(root.path(), "1-2", 0x09, 0x0424, 0x9514);
+    fs::write(root.path().join("bus/usb/devices/1-2/maxchild"), "2\n").unwrap();
+    create_port_attributes(root.path(), "1-2:1.0", "1-2-port1", "configured");
+    create_port_attributes(root.path(), "1-2:1.0", "1-2-port2", "not attached");
+    create_device(root.path(), "1-2.1", 0x00, 0x0483, 0x374b);
+    fs::write(root.path().join("bus/usb/devices/1-2.1/devnum"), "5\n").unwrap();
+    fs::write(root.path().join("bus/usb/devices/1-2.1/speed"), "480\n").unwrap();
+
+    let sysfs = Sysfs::new(root.path());
+    let hub = sysfs.hub(&"1-2".parse::<Location>().unwrap()).unwrap();
+    let state = HubState::from_sysfs(&hub, &sysfs, &Quirks::builtin());
+    assert_eq!(state.device.vendor_id, 0x0424);
+    assert_eq!(state.device.busnum, 1);
+    assert_eq!(state.device.address, None);
+    assert_eq!(state.port_count, 2);
+    assert_eq!(state.switching_mode, None);
+    assert_eq!(state.container_id, None);
+    assert_eq!(state.quirks, vec!["ganged"]);
+    assert_eq!(state.ports[0].status, None);
+    let device = state.ports[0].device.as_ref().unwrap();
+    assert_eq!(device.address, Some(5));
+    assert_eq!(device.speed, Some("high"));
+    assert_eq!(state.ports[1].device, None);
+}
+
+#[test]
+fn port_authorized() {
+    let root = tempfile::tempdir().unwrap();
+    create_device(root.path(), "1-2.4", 0x00, 0x0483, 0x374b);
+    let authorized = root.path().join("bus/usb/devices/1-2.4/authorized");
+    fs::write(&authorized, "1\n").unwrap();
+    let sysfs = Sysfs::new(root.path());
+    let hub = "1-2".parse::<Location>().unwrap();
+
+    assert!(sysfs.port_authorized(&hub, 4).unwrap());
+    sysfs.set_port_authorized(&hub, 4, false).unwrap();
+    assert_eq!(fs::read_to_string(&authorized).unwrap(), "0");
+    assert!(!sysfs.port_authorized(&hub, 4).unwrap());
+    sysfs.set_port_authorized(&hub, 4, true).unwrap();
+    assert_eq!(fs::read_to_string(&authorized).unwrap(), "1");
+
+    assert!(matches!(
+        sysfs.set_port_authorized(&hub, 3, false),
+        Err(Error::NoDevice { location }) if location == hub.child(3)
+    ));
+}
@@ -0,0 +1,60 @@
+use usb_hub_control::Location;
+
+#[test]
+fn parse_and_format() {
+    for s in ["1-2", "1-2.3.4", "usb3", "12-1.15"] {
+        assert_eq!(s.parse::<Location>().unwrap().to_string(), s);
+    }
+    assert_eq!(
+        "1-2.3".parse::<Location>().unwrap(),
+        Location::new(1, &[2, 3])
+    );
+    assert_eq!("usb2".parse::<Location>().unwrap(), Location::new(2, &[]));
+}
+
+#[test]
+fn parse_invalid() {
+    for s in [
+        "", "1", "1-", "1-2.", "1-0", "+1-2", "1-+2", "1-2.+3", "usb+1", "usb", "1-2.a", "1-256",
+        " 1-2",
+    ] {
+        assert!(s.parse::<Location>().is_err(), "{:?} parsed", s);
+    }
+}
+
+#[test]
+fn relations() {
+    let hub = Location::new(1, &[2]);
+    let device = hub.child(3);
+    assert_eq!(device, Location::new(1, &[2, 3]));
+    assert_eq!(device.depth(), 2);
+    assert_eq!(device.parent(), Some((hub.clone(), 3)));
+    assert_eq!(Location::new(1, &[]).parent(), None);
+    assert!(device.is_within(&hub));
+    assert!(hub.is_within(&hub));
+    assert!(!hub.is_within(&device));
+    assert!(!Location::new(2, &[2, 3]).is_within(&hub));
+}
+
+#[test]
+fn hub_before_attached_devices() {
+    // Topology iterates in location order and relies on a hub sorting before its devices
+    let mut locations = vec![
+        Location::new(1, &[2, 3]),
+        Location::new(1, &[10]),
+        Location::new(1, &[2]),
+        Location::new(1, &[]),
+        Location::new(1, &[2, 1, 4]),
+    ];
+    locations.sort();
+    assert_eq!(
+        locations,
+        vec![
+            Location::new(1, &[]),
+            Location::new(1, &[2]),
+            Location::new(1, &[2, 1, 4]),
+            Location::new(1, &[2, 3]),
+            Location::new(1, &[10]),
+        ]
+    );
+}
@@ -0,0 +1,45 @@
+use usb_hub_control::{Location, UdevRule};
+
+#[test]
+fn sysfs_rule_matches_hub_only() {
+    let rule = UdevRule {
+        location: Some("1-2".parse::<Location>().unwrap()),
+        vendor_id: 0x05e3,
+        product_id: 0x0610,
+        description: String::from("GenesysLogic USB2.1 Hub"),
+    };
+    let text = rule.format("plugdev", "0660", true);
+    let lines = text.lines().collect::<Vec<&str>>();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], "# GenesysLogic USB2.1 Hub");
+    assert_eq!(
+        lines[1],
+        "SUBSYSTEM==\"usb\", DEVTYPE==\"usb_device\", KERNEL==\"1-2\", \
+         ATTR{idVendor}==\"05e3\", ATTR{idProduct}==\"0610\", MODE=\"0660\", GROUP=\"plugdev\""
+    );
+    assert_eq!(
+        lines[2],
+        "ACTION!=\"remove\", SUBSYSTEM==\"usb\", DEVTYPE==\"usb_device\", KERNEL==\"1-2\", \
+         ATTR{idVendor}==\"05e3\", ATTR{idProduct}==\"0610\", \
+         RUN+=\"/bin/sh -c 'chgrp plugdev $sys$devpath/*:*/*-port*/disable; \
+         chmod g+w $sys$devpath/*:*/*-port*/disable'\""
+    );
+    // Matching ancestors would extend the access to downstream hubs
+    assert!(!text.contains("KERNELS=="));
+    assert!(!text.contains("ATTRS{"));
+}
+
+#[test]
+fn rule_without_location() {
+    let rule = UdevRule {
+        location: None,
+        vendor_id: 0x2109,
+        product_id: 0x3431,
+        description: String::new(),
+    };
+    assert_eq!(
+        rule.format("plugdev", "0660", false),
+        "SUBSYSTEM==\"usb\", DEVTYPE==\"usb_device\", ATTR{idVendor}==\"2109\", \
+         ATTR{idProduct}==\"3431\", MODE=\"0660\", GROUP=\"plugdev\"\n"
+    );
+}